@@ -6,6 +6,9 @@ use criterion::{Benchmark, Criterion};
 
 extern crate advent_2018;
 use advent_2018::fifteen;
+use advent_2018::five;
+use advent_2018::nine;
+use advent_2018::six;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let benchmark = Benchmark::new("15b", |b| b.iter(|| fifteen::fifteen_b("src/inputs/15.txt")))
@@ -13,6 +16,45 @@ fn criterion_benchmark(c: &mut Criterion) {
         .measurement_time(time::Duration::new(180, 0));
 
     c.bench("15b", benchmark);
+
+    // Day 9's marble game, with its circle kept in a pair of deques vs. in an index-linked ring.
+    let benchmark = Benchmark::new("deque", |b| {
+        b.iter(|| nine::play_with_circle::<nine::game::DequeCircle>(413, 7108200, Default::default()))
+    })
+    .with_function("ring", |b| {
+        b.iter(|| nine::play_with_circle::<nine::game::RingCircle>(413, 7108200, Default::default()))
+    })
+    .sample_size(20);
+
+    c.bench("9b", benchmark);
+
+    // Day 6's safe region, adding up each cell's distance to every location vs. adding up
+    // each row's and each column's distances once.
+    let locations = six::load_locations("src/inputs/6.txt");
+    let more_locations = locations.clone();
+    let benchmark = Benchmark::new("cell by cell", move |b| {
+        b.iter(|| six::safe_region_cell_by_cell(&locations, 10_000))
+    })
+    .with_function("separable", move |b| {
+        b.iter(|| six::safe_region(&more_locations, 10_000))
+    })
+    .sample_size(20);
+
+    c.bench("6b", benchmark);
+
+    // Day 5's most problematic unit type, reacting the whole polymer from scratch for each type
+    // vs. reacting it once and starting from what's left.
+    let polymer = five::Polymer::new(std::fs::read_to_string("src/inputs/5.txt").unwrap().trim());
+    let same_polymer = polymer.clone();
+    let benchmark = Benchmark::new("from scratch", move |b| {
+        b.iter(|| polymer.most_problematic_unit_type_from_scratch())
+    })
+    .with_function("from reacted", move |b| {
+        b.iter(|| same_polymer.most_problematic_unit_type())
+    })
+    .sample_size(20);
+
+    c.bench("5b", benchmark);
 }
 
 criterion_group!(benches, criterion_benchmark);
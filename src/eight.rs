@@ -1,61 +1,214 @@
 use std::fs;
 
-#[derive(Debug)]
-struct Node {
-    metadata: Vec<u32>,
-    children: Vec<Node>,
+use hashbrown::HashMap;
+
+use crate::util;
+
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub metadata: Vec<u32>,
+    pub children: Vec<Node>,
 }
 
-fn parse_license_text() -> Vec<u32> {
-    let contents = fs::read_to_string("src/inputs/8.txt").unwrap();
-    let mut license_data_buffer: Vec<u32> = contents.trim().split(' ').map(|x| x.parse::<u32>().unwrap()).collect();
+impl Node {
+    /// Returns an iterator over this node and all of its descendants, parents before children.
+    pub fn iter(&self) -> impl Iterator<Item = &Node> {
+        self.iter_with_depth().map(|(_, node)| node)
+    }
+
+    /// Like iter(), but also yields each node's depth, where this node's depth is 1.
+    fn iter_with_depth(&self) -> impl Iterator<Item = (usize, &Node)> {
+        let mut stack = vec![(1, self)];
 
-    license_data_buffer.reverse();
-    license_data_buffer
+        std::iter::from_fn(move || {
+            let (depth, node) = stack.pop()?;
+            stack.extend(node.children.iter().rev().map(|child| (depth + 1, child)));
+            Some((depth, node))
+        })
+    }
+
+    /// Turns this node back into the license file's format: a header, then each child, then the metadata.
+    pub fn encode(&self) -> String {
+        enum Step<'a> {
+            Node(&'a Node),
+            Metadata(&'a Node),
+        }
+
+        let mut numbers = vec![];
+        let mut steps = vec![Step::Node(self)];
+
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Node(node) => {
+                    numbers.push(node.children.len() as u32);
+                    numbers.push(node.metadata.len() as u32);
+                    steps.push(Step::Metadata(node));
+                    steps.extend(node.children.iter().rev().map(Step::Node));
+                }
+                Step::Metadata(node) => numbers.extend(&node.metadata),
+            }
+        }
+
+        numbers
+            .iter()
+            .map(|number| number.to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Returns one line per node, indented two spaces per level, listing its metadata and value.
+    pub fn pretty_print(&self) -> String {
+        self.iter_with_depth()
+            .zip(node_values(self))
+            .map(|((depth, node), value)| {
+                let metadata = node
+                    .metadata
+                    .iter()
+                    .map(|entry| entry.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+
+                format!("{}metadata: {}; value: {}", "  ".repeat(depth - 1), metadata, value)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn stats(&self) -> TreeStats {
+        TreeStats {
+            depth: self.iter_with_depth().map(|(depth, _)| depth).max().unwrap(),
+            node_count: self.iter().count(),
+            fan_out: util::frequencies(self.iter().map(|node| node.children.len())),
+        }
+    }
 }
 
-fn parse_node(license_data_buffer: &mut Vec<u32>) -> Node {
+/// The shape of a tree of nodes.
+#[derive(Debug, PartialEq)]
+pub struct TreeStats {
+    /// The number of levels in the tree; a lone node has a depth of 1.
+    pub depth: usize,
+    pub node_count: usize,
+    /// Maps each number of children to how many nodes have that many children.
+    pub fan_out: HashMap<usize, u32>,
+}
+
+impl Drop for Node {
+    /// The compiler-generated drop would recurse once per level of the tree,
+    /// so a deep enough tree would overflow the stack on its way out.
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.children);
+
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+/// Ways in which a license file can be malformed.
+/// Each one carries the offset of the offending number, counting from 0 at the start of the file.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    /// The number at this offset isn't a valid u32.
+    InvalidNumber { offset: usize },
+    /// The file ended in the middle of a node; another number was expected at this offset.
+    UnexpectedEnd { offset: usize },
+    /// The root node ended before the file did; the first number that wasn't part of it is at this offset.
+    TrailingData { offset: usize },
+}
+
+/// A node that's in the middle of being parsed.
+struct PartialNode {
+    node: Node,
+    children_left: u32,
+    metadata_count: u32,
+}
+
+/// Hands out a license file's numbers one at a time, keeping track of where it is.
+struct LicenseReader {
+    numbers: Vec<u32>,
+    offset: usize,
+}
+
+impl LicenseReader {
+    fn next_number(&mut self) -> Result<u32, ParseError> {
+        let number = self
+            .numbers
+            .get(self.offset)
+            .cloned()
+            .ok_or(ParseError::UnexpectedEnd { offset: self.offset })?;
+        self.offset += 1;
+
+        Ok(number)
+    }
+
     // Specifically, a node consists of:
     // * A header, which is always exactly two numbers:
     //     * The quantity of child nodes.
     //     * The quantity of metadata entries.
-    let children_count = license_data_buffer.pop().unwrap();
-    let metadata_count = license_data_buffer.pop().unwrap();
+    fn read_header(&mut self) -> Result<PartialNode, ParseError> {
+        Ok(PartialNode {
+            children_left: self.next_number()?,
+            metadata_count: self.next_number()?,
+            node: Node {
+                metadata: vec![],
+                children: vec![],
+            },
+        })
+    }
+}
 
-    let mut node = Node {
-        metadata: vec![],
-        children: vec![],
-    };
+/// Parses a license file into its root node.
+///
+/// Keeps its own stack of partially-parsed nodes instead of recursing, so that a deep tree
+/// can't overflow the call stack.
+pub fn parse_license(text: &str) -> Result<Node, ParseError> {
+    let numbers = text
+        .split_whitespace()
+        .enumerate()
+        .map(|(offset, number)| number.parse::<u32>().map_err(|_| ParseError::InvalidNumber { offset }))
+        .collect::<Result<Vec<u32>, ParseError>>()?;
+    let mut reader = LicenseReader { numbers, offset: 0 };
 
-    // * Zero or more child nodes (as specified in the header).
-    for _ in 0..children_count {
-        node.children.push(parse_node(license_data_buffer));
-    }
+    let mut stack = vec![reader.read_header()?];
 
-    // * One or more metadata entries (as specified in the header).
-    for _ in 0..metadata_count {
-        node.metadata.push(license_data_buffer.pop().unwrap());
-    }
+    loop {
+        let partial = stack.last_mut().unwrap();
 
-    node
-}
+        // * Zero or more child nodes (as specified in the header).
+        if partial.children_left > 0 {
+            partial.children_left -= 1;
+            let child = reader.read_header()?;
+            stack.push(child);
+            continue;
+        }
 
-fn metadata_values(node: Node) -> Vec<u32> {
-    let mut ret = node.metadata.clone();
+        // * One or more metadata entries (as specified in the header).
+        let mut finished = stack.pop().unwrap();
+        for _ in 0..finished.metadata_count {
+            finished.node.metadata.push(reader.next_number()?);
+        }
 
-    for child in node.children {
-        ret.extend(metadata_values(child));
+        match stack.last_mut() {
+            Some(parent) => parent.node.children.push(finished.node),
+            None if reader.offset < reader.numbers.len() => {
+                return Err(ParseError::TrailingData { offset: reader.offset });
+            }
+            None => return Ok(finished.node),
+        }
     }
+}
 
-    ret
+fn parse_license_text() -> Node {
+    let contents = fs::read_to_string("src/inputs/8.txt").unwrap();
+    parse_license(&contents).unwrap()
 }
 
 /// The first check done on the license file is to simply add up all of the metadata entries.
 pub fn eight_a() -> u32 {
-    let mut license_data_buffer = parse_license_text();
-    let node = parse_node(&mut license_data_buffer);
+    let root = parse_license_text();
 
-    metadata_values(node).iter().sum()
+    root.iter().flat_map(|node| node.metadata.iter()).sum()
 }
 
 /// The second check is slightly more complicated: you need to find the value of the root node.
@@ -66,32 +219,54 @@ pub fn eight_a() -> u32 {
 /// referenced by the metadata entries. If a referenced child node does not exist, that reference
 /// is skipped. A child node can be referenced multiple time and counts each time it is referenced.
 /// A metadata entry of 0 does not refer to any child node.
-fn node_value(node: &Node) -> u32 {
-    if node.children.is_empty() {
-        node.metadata.iter().sum()
-    } else {
-        let mut sum = 0;
-
-        for &index in &node.metadata {
-            // A metadata entry of 1 refers to the first child node, 2 to the second,
-            // 3 to the third, and so on.
-            let index: usize = index as usize - 1;
-
-            if index < node.children.len() {
-                sum += node_value(&node.children[index]);
+fn node_value(root: &Node) -> u32 {
+    node_values(root)[0]
+}
+
+/// Returns the value of every node in the tree, in the same order as root.iter().
+fn node_values(root: &Node) -> Vec<u32> {
+    // Visit each node twice: once on the way down, to queue up its children, and once more
+    // after all of its children's values have been pushed onto `child_values`, in order.
+    let mut to_visit = vec![(root, None)];
+    let mut child_values: Vec<u32> = vec![];
+    let mut values = vec![];
+
+    while let Some((node, index)) = to_visit.pop() {
+        let index = match index {
+            None => {
+                // Nodes come off the stack parents-first the first time around, same as in root.iter().
+                values.push(0);
+                to_visit.push((node, Some(values.len() - 1)));
+                to_visit.extend(node.children.iter().rev().map(|child| (child, None)));
+                continue;
             }
-        }
+            Some(index) => index,
+        };
+
+        let children = child_values.split_off(child_values.len() - node.children.len());
 
-        sum
+        let value = if node.children.is_empty() {
+            node.metadata.iter().sum()
+        } else {
+            node.metadata
+                .iter()
+                // A metadata entry of 1 refers to the first child node, 2 to the second,
+                // 3 to the third, and so on.
+                .filter_map(|&entry| (entry as usize).checked_sub(1))
+                .filter_map(|child_index| children.get(child_index))
+                .sum()
+        };
+
+        values[index] = value;
+        child_values.push(value);
     }
+
+    values
 }
 
 /// What is the value of the root node?
 pub fn eight_b() -> u32 {
-    let mut license_data_buffer = parse_license_text();
-    let node = parse_node(&mut license_data_buffer);
-
-    node_value(&node)
+    node_value(&parse_license_text())
 }
 
 #[cfg(test)]
@@ -103,4 +278,83 @@ mod tests {
         assert_eq!(eight_a(), 40309);
         assert_eq!(eight_b(), 28779);
     }
+
+    #[test]
+    fn test_sample() {
+        let root = parse_license(&fs::read_to_string("src/inputs/8_sample.txt").unwrap()).unwrap();
+
+        assert_eq!(root.iter().flat_map(|node| node.metadata.iter()).sum::<u32>(), 138);
+        assert_eq!(node_value(&root), 66);
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        assert_eq!(
+            parse_license("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1"),
+            Err(ParseError::UnexpectedEnd { offset: 15 })
+        );
+        assert_eq!(
+            parse_license("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2 7 8"),
+            Err(ParseError::TrailingData { offset: 16 })
+        );
+        assert_eq!(parse_license("0 1 x"), Err(ParseError::InvalidNumber { offset: 2 }));
+        assert_eq!(parse_license(""), Err(ParseError::UnexpectedEnd { offset: 0 }));
+        assert_eq!(parse_license("1 1 0"), Err(ParseError::UnexpectedEnd { offset: 3 }));
+    }
+
+    #[test]
+    fn test_zero_metadata_index() {
+        // The root's metadata refers to child 0, which doesn't exist, and then to child 1.
+        let root = parse_license("1 2 0 1 5 0 1").unwrap();
+        assert_eq!(node_value(&root), 5);
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        let depth = 1_000_000;
+        let mut text = "1 1 ".repeat(depth);
+        text.push_str("0 1 1");
+        text.push_str(&" 1".repeat(depth));
+
+        let root = parse_license(&text).unwrap();
+
+        assert_eq!(root.iter().count(), depth + 1);
+        assert_eq!(node_value(&root), 1);
+    }
+
+    #[test]
+    fn test_encode() {
+        let text = fs::read_to_string("src/inputs/8_sample.txt").unwrap();
+        assert_eq!(parse_license(&text).unwrap().encode(), text.trim());
+
+        let text = fs::read_to_string("src/inputs/8.txt").unwrap();
+        assert_eq!(parse_license(&text).unwrap().encode(), text.trim());
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let root = parse_license(&fs::read_to_string("src/inputs/8_sample.txt").unwrap()).unwrap();
+
+        assert_eq!(
+            root.pretty_print(),
+            [
+                "metadata: 1 1 2; value: 66",
+                "  metadata: 10 11 12; value: 33",
+                "  metadata: 2; value: 0",
+                "    metadata: 99; value: 99",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let root = parse_license(&fs::read_to_string("src/inputs/8_sample.txt").unwrap()).unwrap();
+        let stats = root.stats();
+
+        assert_eq!(stats.depth, 3);
+        assert_eq!(stats.node_count, 4);
+        assert_eq!(stats.fan_out.len(), 3);
+        assert_eq!((stats.fan_out[&0], stats.fan_out[&1], stats.fan_out[&2]), (2, 1, 1));
+    }
 }
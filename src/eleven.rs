@@ -1,3 +1,11 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs;
+
+use rayon::prelude::*;
+
+use crate::util::SummedAreaTable;
+
 fn power_level(x: u32, y: u32, serial: u32) -> i32 {
     // Find the fuel cell's rack ID, which is its X coordinate plus 10.
     let rack_id = x + 10;
@@ -19,80 +27,128 @@ fn power_level(x: u32, y: u32, serial: u32) -> i32 {
     power as i32 - 5
 }
 
-const SERIAL: u32 = 6303;
-const GRID_WIDTH: usize = 300;
-const GRID_HEIGHT: usize = 300;
+/// A square of fuel cells, identified by the 1-based X,Y coordinate of its top-left fuel cell and its size.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Square {
+    pub x: usize,
+    pub y: usize,
+    pub size: usize,
+    pub power: i32,
+}
 
-fn coordinates() -> Vec<(usize, usize)> {
-    (0..GRID_WIDTH)
-        .flat_map(|x| (0..GRID_HEIGHT).map(move |y| (x, y)))
-        .collect::<Vec<(usize, usize)>>()
+impl Square {
+    /// Orders squares by total power; ties go to the smallest square, and then to the one nearest the top-left.
+    fn rank(&self) -> (i32, Reverse<usize>, Reverse<usize>, Reverse<usize>) {
+        (self.power, Reverse(self.size), Reverse(self.x), Reverse(self.y))
+    }
 }
 
-fn make_grid() -> Vec<Vec<i32>> {
-    let mut grid = vec![vec![0; GRID_HEIGHT]; GRID_WIDTH];
+/// "The power grid is a grid of fuel cells, each of which has a power level that depends on
+/// the grid's serial number (your puzzle input)."
+pub struct PowerGrid {
+    table: SummedAreaTable<i32>,
+}
 
-    for (x, y) in coordinates() {
-        grid[x][y] = power_level(x as u32 + 1, y as u32 + 1, SERIAL);
+impl PowerGrid {
+    pub fn new(serial: u32, width: usize, height: usize) -> Self {
+        let grid = (0..width)
+            .map(|x| {
+                (0..height)
+                    .map(|y| power_level(x as u32 + 1, y as u32 + 1, serial))
+                    .collect()
+            })
+            .collect::<Vec<Vec<i32>>>();
+
+        PowerGrid::from_power_levels(&grid)
     }
 
-    grid
-}
+    /// Returns a PowerGrid whose fuel cells have the given power levels, indexed as grid[x][y].
+    pub fn from_power_levels(grid: &[Vec<i32>]) -> Self {
+        PowerGrid {
+            table: SummedAreaTable::new(grid),
+        }
+    }
 
-/// See https://en.wikipedia.org/wiki/Summed-area_table
-fn make_summed_area_table(grid: &[Vec<i32>]) -> Vec<Vec<i32>> {
-    let mut table = vec![vec![0; GRID_HEIGHT]; GRID_WIDTH];
+    fn max_square_size(&self) -> usize {
+        self.table.width().min(self.table.height())
+    }
+
+    /// Returns the `size`x`size` square with the largest total power,
+    /// or None if `size` is 0 or the grid is too small to fit a square that big.
+    pub fn best_square(&self, size: usize) -> Option<Square> {
+        let (x, y, power) = square_with_most_power(&self.table, size)?;
+
+        Some(Square {
+            x: x + 1,
+            y: y + 1,
+            size,
+            power,
+        })
+    }
 
-    for y in 0..GRID_HEIGHT {
-        for x in 0..GRID_WIDTH {
-            let above = if y > 0 { table[x][y - 1] } else { 0 };
+    /// Returns the square of any size with the largest total power, or None if the grid is empty.
+    /// Sizes are searched in parallel, and ties go to the smallest square.
+    pub fn best_square_any_size(&self) -> Option<Square> {
+        (1..self.max_square_size() + 1)
+            .into_par_iter()
+            .filter_map(|size| self.best_square(size))
+            .max_by_key(Square::rank)
+    }
 
-            let left = if x > 0 { table[x - 1][y] } else { 0 };
+    /// Returns the `k` squares of any size with the largest total power, most powerful first.
+    pub fn top_squares(&self, k: usize) -> Vec<Square> {
+        let mut squares = (1..self.max_square_size() + 1)
+            .into_par_iter()
+            .flat_map(|size| self.top_squares_of_size(size, k))
+            .collect::<Vec<Square>>();
 
-            let above_left = if x > 0 && y > 0 { table[x - 1][y - 1] } else { 0 };
+        squares.sort_by_key(|square| Reverse(square.rank()));
+        squares.truncate(k);
 
-            table[x][y] = grid[x][y] + above + left - above_left;
-        }
+        squares
     }
 
-    table
+    fn top_squares_of_size(&self, size: usize, k: usize) -> Vec<Square> {
+        // A min-heap of the best `k` squares seen so far, so the worst of them is always on top, ready to be bumped.
+        // It never holds more than one square past however many squares of this size there are.
+        let num_squares = (self.table.width() - size + 1) * (self.table.height() - size + 1);
+        let mut heap = BinaryHeap::with_capacity(k.min(num_squares) + 1);
+
+        for x in 0..=self.table.width() - size {
+            for y in 0..=self.table.height() - size {
+                let square = Square {
+                    x: x + 1,
+                    y: y + 1,
+                    size,
+                    power: self.table.rectangle_sum(x, y, size, size),
+                };
+
+                heap.push(Reverse(square.rank()));
+
+                if heap.len() > k {
+                    heap.pop();
+                }
+            }
+        }
+
+        heap.into_iter()
+            .map(|Reverse((power, Reverse(size), Reverse(x), Reverse(y)))| Square { x, y, size, power })
+            .collect()
+    }
 }
 
-fn square_with_most_power(table: &[Vec<i32>], square_side_len: usize) -> (usize, usize, i32) {
+fn square_with_most_power(table: &SummedAreaTable<i32>, square_side_len: usize) -> Option<(usize, usize, i32)> {
+    if square_side_len == 0 || square_side_len > table.width().min(table.height()) {
+        return None;
+    }
+
     let mut ret_x = 0;
     let mut ret_y = 0;
-    let mut most_power = std::i32::MIN;
-
-    // Use the summed-area-table algorithm to calculate the total power
-    // for each square.
-
-    for x in square_side_len - 1..GRID_WIDTH {
-        for y in square_side_len - 1..GRID_HEIGHT {
-            // The square's power is:
-            // * The value of the summed-area-table's entry for the bottom-right coordinate of the square
-            // * minus the value of the summed-area-table's entry for the coordinate `square_side_len` due north
-            // * minus the value of the summed-area-table's entry for the coordinate `square_side_len` due west
-            // * plus the value of the summed-area-table's entry for the coordinate `square_side_len` due northwest
-
-            let above = if y >= square_side_len {
-                table[x][y - square_side_len]
-            } else {
-                0
-            };
-
-            let left = if x >= square_side_len {
-                table[x - square_side_len][y]
-            } else {
-                0
-            };
-
-            let above_left = if x >= square_side_len && y >= square_side_len {
-                table[x - square_side_len][y - square_side_len]
-            } else {
-                0
-            };
-
-            let square_power = table[x][y] - above - left + above_left;
+    let mut most_power = i32::MIN;
+
+    for x in 0..=table.width() - square_side_len {
+        for y in 0..=table.height() - square_side_len {
+            let square_power = table.rectangle_sum(x, y, square_side_len, square_side_len);
 
             if square_power > most_power {
                 most_power = square_power;
@@ -102,21 +158,28 @@ fn square_with_most_power(table: &[Vec<i32>], square_side_len: usize) -> (usize,
         }
     }
 
-    (ret_x - (square_side_len - 1), ret_y - (square_side_len - 1), most_power)
+    Some((ret_x, ret_y, most_power))
+}
+
+fn parse_input() -> u32 {
+    fs::read_to_string("src/inputs/11.txt").unwrap().trim().parse().unwrap()
 }
 
+const GRID_WIDTH: usize = 300;
+const GRID_HEIGHT: usize = 300;
+
 /// Each fuel cell has a coordinate ranging from 1 to 300 in both the X (horizontal)
 /// and Y (vertical) direction. In X,Y notation, the top-left cell is 1,1,
 /// and the top-right cell is 300,1.
-
+///
 /// Your goal is to find the 3x3 square which has the largest total power.
 /// What is the X,Y coordinate of the top-left fuel cell of the 3x3 square with the largest total power?
 pub fn eleven_a() -> (usize, usize) {
-    let grid = make_grid();
-    let table = make_summed_area_table(&grid);
-    let (x, y, _) = square_with_most_power(&table, 3);
+    let square = PowerGrid::new(parse_input(), GRID_WIDTH, GRID_HEIGHT)
+        .best_square(3)
+        .unwrap();
 
-    (x + 1, y + 1)
+    (square.x, square.y)
 }
 
 /// You now must find the square of any size with the largest total power. Identify this
@@ -124,25 +187,11 @@ pub fn eleven_a() -> (usize, usize) {
 /// a 9x9 square with a top-left corner of 3,5 is identified as 3,5,9.
 /// What is the X,Y,size identifier of the square with the largest total power?
 pub fn eleven_b() -> (usize, usize, usize) {
-    let grid = make_grid();
-    let table = make_summed_area_table(&grid);
-    let mut max_power = 0;
-    let mut x = 0;
-    let mut y = 0;
-    let mut square_side_len = 0;
-
-    for size in 1..=300 {
-        let (xx, yy, square_power) = square_with_most_power(&table, size);
-
-        if square_power > max_power {
-            x = xx;
-            y = yy;
-            max_power = square_power;
-            square_side_len = size;
-        }
-    }
+    let square = PowerGrid::new(parse_input(), GRID_WIDTH, GRID_HEIGHT)
+        .best_square_any_size()
+        .unwrap();
 
-    (x + 1, y + 1, square_side_len)
+    (square.x, square.y, square.size)
 }
 
 #[cfg(test)]
@@ -162,4 +211,107 @@ mod test {
         assert_eq!(power_level(217, 196, 39), 0);
         assert_eq!(power_level(101, 153, 71), 4);
     }
+
+    #[test]
+    fn test_power_grid() {
+        let grid = PowerGrid::new(18, 300, 300);
+        assert_eq!(
+            grid.best_square(3),
+            Some(Square {
+                x: 33,
+                y: 45,
+                size: 3,
+                power: 29
+            })
+        );
+        assert_eq!(
+            grid.best_square_any_size(),
+            Some(Square {
+                x: 90,
+                y: 269,
+                size: 16,
+                power: 113
+            })
+        );
+
+        let grid = PowerGrid::new(42, 300, 300);
+        assert_eq!(
+            grid.best_square(3),
+            Some(Square {
+                x: 21,
+                y: 61,
+                size: 3,
+                power: 30
+            })
+        );
+        assert_eq!(
+            grid.best_square_any_size(),
+            Some(Square {
+                x: 232,
+                y: 251,
+                size: 12,
+                power: 119
+            })
+        );
+    }
+
+    #[test]
+    fn test_top_squares() {
+        let grid = PowerGrid::new(18, 300, 300);
+        let top_squares = grid.top_squares(5);
+
+        assert_eq!(top_squares.len(), 5);
+        assert_eq!(Some(top_squares[0]), grid.best_square_any_size());
+        assert!(top_squares.windows(2).all(|pair| pair[0].power >= pair[1].power));
+        assert_eq!(grid.top_squares(1), vec![grid.best_square_any_size().unwrap()]);
+    }
+
+    #[test]
+    fn test_all_negative_grid() {
+        let grid = PowerGrid::from_power_levels(&[vec![-3, -2, -4], vec![-5, -1, -6], vec![-2, -7, -3]]);
+
+        assert_eq!(
+            grid.best_square_any_size(),
+            Some(Square {
+                x: 2,
+                y: 2,
+                size: 1,
+                power: -1
+            })
+        );
+        assert_eq!(
+            grid.top_squares(3)
+                .iter()
+                .map(|square| square.power)
+                .collect::<Vec<i32>>(),
+            vec![-1, -2, -2]
+        );
+
+        // 9 1x1 squares, 4 2x2 squares and 1 3x3 square.
+        assert_eq!(grid.top_squares(100).len(), 14);
+        assert_eq!(grid.top_squares(usize::MAX), grid.top_squares(14));
+    }
+
+    #[test]
+    fn test_square_sizes() {
+        let grid = PowerGrid::from_power_levels(&[vec![1, 2, 3], vec![4, 5, 6]]);
+
+        assert_eq!(grid.best_square(0), None);
+        assert_eq!(
+            grid.best_square(2),
+            Some(Square {
+                x: 1,
+                y: 2,
+                size: 2,
+                power: 16
+            })
+        );
+        assert_eq!(grid.best_square(3), None);
+        assert_eq!(PowerGrid::new(18, 300, 300).best_square(301), None);
+
+        let empty_grid = PowerGrid::from_power_levels(&[]);
+        assert_eq!(empty_grid.best_square(1), None);
+        assert_eq!(empty_grid.best_square_any_size(), None);
+        assert!(empty_grid.top_squares(3).is_empty());
+    }
 }
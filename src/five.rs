@@ -8,48 +8,183 @@ use rayon::prelude::*;
 /// are units with the same type but opposite polarity, whereas r and s are entirely different types and do not react.
 ///
 /// In abBA, bB destroys itself, leaving aA. As above, this then destroys itself, leaving nothing.
+pub trait Reaction {
+    /// Whether `a` and `b` destroy each other when `b` is right after `a`.
+    fn reacts(&self, a: char, b: char) -> bool;
 
-/// Turns "cabBA" into "c".
-fn react_polymer(polymer: Vec<u8>) -> Vec<u8> {
-    let mut ret = Vec::<u8>::with_capacity(polymer.len());
-
-    // This approach taken from forrestthewoods; not a direct copy-paste, but very very very very similar.
-    // See previous commits in this file to see my original solution, which worked fine but was way too slow.
-    // My solution involved a `react_polymer_one_step` function which was called over and over by `react_polymer()`.
-    // Forrest's solution recognizes that the polymer buffer being operated on can function as a stack,
-    // so there's no need to do a series of one-step reactions, it can all be done in one pass. Clever!
-    for character in polymer {
-        let should_push = match ret.last() {
-            None => true,
-            Some(&prev_char) => {
-                !prev_char.eq_ignore_ascii_case(&character)
-                    || prev_char.is_ascii_lowercase() == character.is_ascii_lowercase()
+    /// Returns `unit`'s type, which is the same for every polarity of that unit.
+    fn unit_type(&self, unit: char) -> char;
+
+    /// Whether a polymer always ends up the same whichever order its reactions happen in,
+    /// even after taking out every unit of some type. Polymer uses shortcuts that rely on this
+    /// when it's true.
+    fn is_confluent(&self) -> bool {
+        false
+    }
+}
+
+/// Units' types are letters, and their polarity is their capitalization.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Capitalization;
+
+impl Reaction for Capitalization {
+    fn reacts(&self, a: char, b: char) -> bool {
+        a != b && a.eq_ignore_ascii_case(&b)
+    }
+
+    fn unit_type(&self, unit: char) -> char {
+        unit.to_ascii_lowercase()
+    }
+
+    /// A unit only ever reacts with its neighbors of the same type, so taking units out
+    /// of a reacted polymer can't undo any of its reactions, only allow new ones.
+    fn is_confluent(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Polymer<R: Reaction = Capitalization> {
+    units: Vec<char>,
+    reaction: R,
+}
+
+impl Polymer {
+    pub fn new(units: &str) -> Polymer {
+        Polymer::with_reaction(units, Capitalization)
+    }
+}
+
+impl<R: Reaction + Clone> Polymer<R> {
+    /// Returns a polymer whose units react according to `reaction`.
+    pub fn with_reaction(units: &str, reaction: R) -> Polymer<R> {
+        Polymer {
+            units: units.chars().collect(),
+            reaction,
+        }
+    }
+
+    pub fn units(&self) -> &[char] {
+        &self.units
+    }
+
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+
+    /// Returns every unit type in the polymer, in order.
+    pub fn unit_types(&self) -> Vec<char> {
+        let mut unit_types: Vec<char> = self.units.iter().map(|&unit| self.reaction.unit_type(unit)).collect();
+        unit_types.sort();
+        unit_types.dedup();
+
+        unit_types
+    }
+
+    /// Turns "cabBA" into "c".
+    pub fn react(&self) -> Polymer<R> {
+        let mut ret = Vec::with_capacity(self.units.len());
+        self.react_into(self.units.iter().cloned(), &mut ret);
+
+        Polymer {
+            units: ret,
+            reaction: self.reaction.clone(),
+        }
+    }
+
+    /// Fully reacts `units`, leaving whatever's left in `ret`, which is cleared first.
+    fn react_into(&self, units: impl Iterator<Item = char>, ret: &mut Vec<char>) {
+        ret.clear();
+
+        // This approach taken from forrestthewoods; not a direct copy-paste, but very very very very similar.
+        // See previous commits in this file to see my original solution, which worked fine but was way too slow.
+        // My solution involved a `react_polymer_one_step` function which was called over and over by `react_polymer()`.
+        // Forrest's solution recognizes that the polymer buffer being operated on can function as a stack,
+        // so there's no need to do a series of one-step reactions, it can all be done in one pass. Clever!
+        for unit in units {
+            match ret.last() {
+                Some(&prev_unit) if self.reaction.reacts(prev_unit, unit) => {
+                    ret.pop();
+                }
+                _ => ret.push(unit),
             }
-        };
+        }
+    }
 
-        if should_push {
-            ret.push(character);
-        } else {
-            ret.pop();
+    /// Returns a copy of this polymer with every unit of type `unit_type` taken out, regardless of polarity.
+    pub fn without(&self, unit_type: char) -> Polymer<R> {
+        Polymer {
+            units: self
+                .units
+                .iter()
+                .cloned()
+                .filter(|&unit| self.reaction.unit_type(unit) != unit_type)
+                .collect(),
+            reaction: self.reaction.clone(),
         }
     }
 
-    ret
+    /// Returns the unit type whose removal lets the polymer react down the furthest,
+    /// along with how many units are left when it's removed. If more than one type
+    /// does equally well, returns the one that's first in unit_types().
+    /// Returns None if the polymer is empty.
+    ///
+    /// If the reaction is confluent, rather than starting over from this polymer for each unit type,
+    /// this reacts it once and starts from what's left, skipping over each type's units as it goes.
+    /// Each batch of unit types that rayon hands out reuses one buffer for all of its reactions, too,
+    /// rather than copying the polymer for every unit type. Otherwise, this is the same as
+    /// most_problematic_unit_type_from_scratch.
+    pub fn most_problematic_unit_type(&self) -> Option<(char, usize)>
+    where
+        R: Send + Sync,
+    {
+        if !self.reaction.is_confluent() {
+            return self.most_problematic_unit_type_from_scratch();
+        }
+
+        let reacted = self.react();
+
+        // Some unit types might have reacted away entirely, and taking those out leaves `reacted` as it is.
+        self.unit_types()
+            .par_iter()
+            .map_init(
+                || Vec::with_capacity(reacted.len()),
+                |buffer, &unit_type| {
+                    let units = reacted
+                        .units
+                        .iter()
+                        .cloned()
+                        .filter(|&unit| reacted.reaction.unit_type(unit) != unit_type);
+                    reacted.react_into(units, buffer);
+
+                    (buffer.len(), unit_type)
+                },
+            )
+            .min()
+            .map(|(len, unit_type)| (unit_type, len))
+    }
+
+    /// Like most_problematic_unit_type, but copies the whole unreacted polymer for each unit type and reacts it from scratch.
+    pub fn most_problematic_unit_type_from_scratch(&self) -> Option<(char, usize)>
+    where
+        R: Send + Sync,
+    {
+        self.unit_types()
+            .par_iter()
+            .map(|&unit_type| (self.without(unit_type).react().len(), unit_type))
+            .min()
+            .map(|(len, unit_type)| (unit_type, len))
+    }
 }
 
 /// How many units remain after fully reacting the polymer you scanned?
 pub fn five_a() -> usize {
     let contents = fs::read_to_string("src/inputs/5.txt").unwrap();
-    react_polymer(contents.trim().as_bytes().to_vec()).len()
-}
-
-fn buf_without_char(buf: &[u8], to_remove: u8) -> Vec<u8> {
-    let to_remove_uppercase = to_remove.to_ascii_uppercase();
-
-    buf.iter()
-        .filter(|&&character| character != to_remove && character != to_remove_uppercase)
-        .cloned()
-        .collect()
+    Polymer::new(contents.trim()).react().len()
 }
 
 /// One of the unit types is causing problems; it's preventing the polymer from
@@ -58,13 +193,9 @@ fn buf_without_char(buf: &[u8], to_remove: u8) -> Vec<u8> {
 /// fully react the remaining polymer, and measure its length.
 pub fn five_b() -> usize {
     let contents = fs::read_to_string("src/inputs/5.txt").unwrap();
-    let contents = contents.trim().as_bytes().to_vec();
+    let (_, len) = Polymer::new(contents.trim()).most_problematic_unit_type().unwrap();
 
-    "abcdefghijklmnopqrstuvwxyz"
-        .par_chars()
-        .map(|character| react_polymer(buf_without_char(&contents, character as u8)).len())
-        .min()
-        .unwrap()
+    len
 }
 
 #[cfg(test)]
@@ -75,10 +206,74 @@ mod test {
     fn test_solutions() {
         assert_eq!(five_a(), 9900);
         assert_eq!(five_b(), 4992);
+
+        let contents = fs::read_to_string("src/inputs/5.txt").unwrap();
+        let polymer = Polymer::new(contents.trim());
+        assert_eq!(
+            polymer.most_problematic_unit_type_from_scratch(),
+            polymer.most_problematic_unit_type()
+        );
     }
 
     #[test]
-    fn test_react_polymer() {
-        assert_eq!(react_polymer(b"abBAacIiCdEQseztTi".to_vec()), b"adEQsezi");
+    fn test_react() {
+        let polymer = Polymer::new("abBAacIiCdEQseztTi").react();
+        assert_eq!(polymer.units().iter().collect::<String>(), "adEQsezi");
+    }
+
+    #[test]
+    fn test_sample() {
+        let polymer = Polymer::new("dabAcCaCBAcCcaDA");
+
+        assert_eq!(polymer.react().units().iter().collect::<String>(), "dabCBAcaDA");
+        assert_eq!(polymer.unit_types(), vec!['a', 'b', 'c', 'd']);
+        assert_eq!(polymer.without('a').react().len(), 6);
+        assert_eq!(polymer.without('b').react().len(), 8);
+
+        // "Removing all c/C units was best, producing the answer 4."
+        assert_eq!(polymer.most_problematic_unit_type(), Some(('c', 4)));
+        assert_eq!(polymer.most_problematic_unit_type_from_scratch(), Some(('c', 4)));
+        assert_eq!(Polymer::new("").most_problematic_unit_type(), None);
+    }
+
+    #[test]
+    fn test_fully_reacting_polymer() {
+        for &units in &["aA", "abBA"] {
+            let polymer = Polymer::new(units);
+
+            assert_eq!(polymer.most_problematic_unit_type(), Some(('a', 0)));
+            assert_eq!(
+                polymer.most_problematic_unit_type(),
+                polymer.most_problematic_unit_type_from_scratch()
+            );
+        }
+    }
+
+    /// Digits whose sum is 10 react, so 3 and 7 are opposite polarities of the same type.
+    #[derive(Clone)]
+    struct TensComplement;
+
+    impl Reaction for TensComplement {
+        fn reacts(&self, a: char, b: char) -> bool {
+            a.to_digit(10).unwrap() + b.to_digit(10).unwrap() == 10 && a != '5'
+        }
+
+        fn unit_type(&self, unit: char) -> char {
+            let digit = unit.to_digit(10).unwrap();
+            std::char::from_digit(digit.min(10 - digit), 10).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_custom_reaction() {
+        let polymer = Polymer::with_reaction("1283746928155", TensComplement);
+
+        // 28, 37 and 46 react, which puts 1 next to 9; then the second 28 reacts.
+        // The two 5s are the same polarity, so they stay put.
+        assert_eq!(polymer.react().units().iter().collect::<String>(), "155");
+        assert_eq!(polymer.unit_types(), vec!['1', '2', '3', '4', '5']);
+        assert_eq!(polymer.without('1').react().len(), 2);
+        assert_eq!(polymer.most_problematic_unit_type(), Some(('5', 1)));
+        assert_eq!(polymer.most_problematic_unit_type_from_scratch(), Some(('5', 1)));
     }
 }
@@ -30,14 +30,18 @@ fn main() {
     println!("6b: {}", six::six_b());
     println!("7a: {}", seven::seven_a());
     println!("7b: {}", seven::seven_b());
+    let (lower_bound, critical_path) = seven::seven_b_lower_bound();
+    println!("7b lower bound: {} (critical path {})", lower_bound, critical_path);
     println!("8a: {}", eight::eight_a());
     println!("8b: {}", eight::eight_b());
     println!("9a: {}", nine::nine_a());
     println!("9b: {}", nine::nine_b());
-    // ten::ten() prints out 10a as a side effect and returns 10b.
-    println!("10a:");
-    let ten_b = ten::ten();
-    println!("10b: {}", ten_b);
+    let message = ten::ten();
+    println!("10a: {}", message.text());
+    for row in message.rows() {
+        println!("{}", row);
+    }
+    println!("10b: {}", message.seconds);
     println!("11a: {:?}", eleven::eleven_a());
     println!("11b: {:?}", eleven::eleven_b());
     println!("12a: {}", twelve::twelve_a());
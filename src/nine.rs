@@ -1,62 +1,252 @@
-mod game {
+use std::fs;
+
+use serde_scan::scan;
+
+pub mod game {
     use std::collections::VecDeque;
 
-    /// An implementation of day 9's weird circle-of-marbles game.
+    use crate::util::ring::{NodeId, Ring};
+
+    /// The rules that decide when players score, and how.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Rules {
+        /// "If the marble that is about to be placed has a number which is a multiple of 23, [...]
+        /// the current player keeps the marble they would have placed, adding it to their score."
+        pub scoring_multiple: usize,
+        /// "In addition, the marble 7 marbles counter-clockwise from the current marble is removed from the circle
+        /// and also added to the current player's score."
+        pub steps_back: usize,
+    }
+
+    impl Default for Rules {
+        fn default() -> Self {
+            Rules {
+                scoring_multiple: 23,
+                steps_back: 7,
+            }
+        }
+    }
+
+    /// A turn on which a player scored.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct ScoringEvent {
+        pub player: usize,
+        /// The marble the player would have placed, which they keep instead.
+        pub marble: usize,
+        /// The marble the player took out of the circle.
+        pub removed_marble: usize,
+    }
+
+    impl ScoringEvent {
+        pub fn points(&self) -> usize {
+            self.marble + self.removed_marble
+        }
+    }
+
+    /// A circle of marbles, one of which is the "current marble" in the game's terminology.
+    pub trait Circle {
+        /// Returns a circle that holds only marble 0, with room for `capacity` marbles.
+        fn new(capacity: usize) -> Self;
+
+        /// Moves the current marble `n` marbles clockwise, or `-n` marbles counter-clockwise if `n` is negative.
+        fn rotate(&mut self, n: isize);
+
+        /// Places `marble` immediately clockwise of the current marble, and makes it the current marble.
+        fn insert(&mut self, marble: usize);
+
+        /// Removes the current marble from the circle and returns it.
+        /// The marble that was clockwise of it becomes the current marble.
+        fn remove(&mut self) -> usize;
+
+        fn current_marble(&self) -> usize;
+
+        /// Returns every marble in the circle, in clockwise order, starting with the current marble.
+        fn marbles(&self) -> Vec<usize>;
+    }
+
     /// Uses two deques, `left` and `right`, to represent the circle.
-    /// The marble on the back of `left` is the "current marble" in the game's terminology.
-    /// The game slides through the circle, moving values from self.right to self.left as it moves right,
-    /// and from self.left to self.right as it moves left.
+    /// The marble on the back of `left` is the current marble, so `left` is never empty.
+    /// The circle slides around, moving values from self.right to self.left as it moves clockwise,
+    /// and from self.left to self.right as it moves counter-clockwise.
     /// If it reaches the end of `self.left` or `self.right`, it swaps them.
     /// It's a circle!
-    pub struct MarbleGame {
+    pub struct DequeCircle {
         left: VecDeque<usize>,
         right: VecDeque<usize>,
+    }
+
+    impl DequeCircle {
+        fn wrap_around(&mut self) {
+            std::mem::swap(&mut self.left, &mut self.right);
+        }
+
+        fn move_right(&mut self) {
+            if self.right.is_empty() {
+                self.wrap_around();
+            }
+
+            self.left.push_back(self.right.pop_front().unwrap());
+        }
+
+        fn move_left(&mut self) {
+            self.right.push_front(self.left.pop_back().unwrap());
+
+            // The new current marble is the one on the back of self.right, counter-clockwise of the old one.
+            if self.left.is_empty() {
+                self.wrap_around();
+            }
+        }
+    }
+
+    impl Circle for DequeCircle {
+        fn new(capacity: usize) -> Self {
+            let mut circle = DequeCircle {
+                left: VecDeque::with_capacity(capacity),
+                right: VecDeque::with_capacity(capacity),
+            };
+            circle.left.push_back(0);
+
+            circle
+        }
+
+        fn rotate(&mut self, n: isize) {
+            for _ in 0..n.abs() {
+                if n > 0 {
+                    self.move_right();
+                } else {
+                    self.move_left();
+                }
+            }
+        }
+
+        fn insert(&mut self, marble: usize) {
+            self.left.push_back(marble);
+        }
+
+        fn remove(&mut self) -> usize {
+            let marble = self.left.pop_back().unwrap();
+            self.move_right();
+
+            marble
+        }
+
+        fn current_marble(&self) -> usize {
+            *self.left.back().unwrap()
+        }
+
+        fn marbles(&self) -> Vec<usize> {
+            // The circle runs clockwise from the front of self.left to the back of self.right, and then wraps around.
+            let mut marbles = self
+                .left
+                .iter()
+                .chain(self.right.iter())
+                .cloned()
+                .collect::<Vec<usize>>();
+            marbles.rotate_left(self.left.len() - 1);
+
+            marbles
+        }
+    }
+
+    /// Keeps the marbles in a linked list, so that marbles can be inserted and removed
+    /// without shuffling any others around.
+    pub struct RingCircle {
+        ring: Ring<usize>,
+        current: NodeId,
+    }
+
+    impl Circle for RingCircle {
+        fn new(capacity: usize) -> Self {
+            let mut ring = Ring::with_capacity(capacity);
+            let current = ring.insert_first(0);
+
+            RingCircle { ring, current }
+        }
+
+        fn rotate(&mut self, n: isize) {
+            self.current = self.ring.rotate(self.current, n);
+        }
+
+        fn insert(&mut self, marble: usize) {
+            let mut cursor = self.ring.cursor_mut(self.current);
+            cursor.insert_after(marble);
+            cursor.move_next();
+            self.current = cursor.position();
+        }
+
+        fn remove(&mut self) -> usize {
+            let mut cursor = self.ring.cursor_mut(self.current);
+            let marble = cursor.remove_current();
+            self.current = cursor.position();
+
+            marble
+        }
+
+        fn current_marble(&self) -> usize {
+            *self.ring.get(self.current)
+        }
+
+        fn marbles(&self) -> Vec<usize> {
+            self.ring.iter_from(self.current).cloned().collect()
+        }
+    }
+
+    /// An implementation of day 9's weird circle-of-marbles game.
+    pub struct MarbleGame<C: Circle = RingCircle> {
+        circle: C,
 
         num_players: usize,
         current_player: usize,
 
         next_marble_id: usize,
+        rules: Rules,
     }
 
     impl MarbleGame {
-        pub fn new(num_players: usize, last_marble: usize) -> MarbleGame {
-            let mut game = MarbleGame {
-                left: VecDeque::with_capacity(last_marble),
-                right: VecDeque::with_capacity(last_marble),
-                num_players: num_players,
+        pub fn new(num_players: usize, last_marble: usize, rules: Rules) -> MarbleGame {
+            MarbleGame::with_circle(num_players, last_marble, rules)
+        }
+    }
+
+    impl<C: Circle> MarbleGame<C> {
+        /// Returns a game whose marbles are kept in a `C`.
+        pub fn with_circle(num_players: usize, last_marble: usize, rules: Rules) -> MarbleGame<C> {
+            // With a scoring_multiple of 1, marble 1 would take marble 0 out of the circle and leave it empty.
+            assert!(
+                rules.scoring_multiple >= 2,
+                "scoring_multiple must be at least 2, or the circle runs out of marbles"
+            );
+
+            MarbleGame {
+                circle: C::new(last_marble + 1),
+                num_players,
                 current_player: 0,
                 next_marble_id: 1,
-            };
-            game.left.push_back(0);
-
-            game
+                rules,
+            }
         }
 
         /// Adds a marble to the circle.
         ///
-        /// Returns Some((player_id, points)) if a player scored this round.
+        /// Returns Some(ScoringEvent) if a player scored this round.
         /// Returns None if nobody scored this round.
-        pub fn add_marble(&mut self) -> Option<(usize, usize)> {
+        pub fn add_marble(&mut self) -> Option<ScoringEvent> {
             let mut ret = None;
 
-            if self.next_marble_id % 23 == 0 {
+            if self.next_marble_id.is_multiple_of(self.rules.scoring_multiple) {
                 // The current player scored some points!
-                for _ in 0..7 {
-                    self.move_left();
-                }
-
-                if self.left.is_empty() {
-                    self.wrap_around();
-                }
-
-                ret = Some((self.current_player, self.next_marble_id + self.left.pop_back().unwrap()));
+                self.circle.rotate(-(self.rules.steps_back as isize));
 
                 // "The marble located immediately clockwise of the marble that was removed becomes the new current marble."
-                self.move_right();
+                ret = Some(ScoringEvent {
+                    player: self.current_player,
+                    marble: self.next_marble_id,
+                    removed_marble: self.circle.remove(),
+                });
             } else {
                 // This isn't a score-getting turn, so just place a marble normally.
-                self.move_right();
-                self.left.push_back(self.next_marble_id);
+                self.circle.rotate(1);
+                self.circle.insert(self.next_marble_id);
             }
 
             self.next_marble_id += 1;
@@ -65,47 +255,95 @@ mod game {
             ret
         }
 
-        fn wrap_around(&mut self) {
-            std::mem::swap(&mut self.left, &mut self.right);
-        }
-
-        fn move_right(&mut self) {
-            if self.right.is_empty() {
-                self.wrap_around();
+        /// Returns every marble in the circle, in clockwise order, starting with marble 0,
+        /// or with the current marble if marble 0 has been removed.
+        pub fn circle(&self) -> Vec<usize> {
+            let mut circle = self.circle.marbles();
+            if let Some(zero_index) = circle.iter().position(|&marble| marble == 0) {
+                circle.rotate_left(zero_index);
             }
 
-            self.left.push_back(self.right.pop_front().unwrap());
+            circle
         }
 
-        fn move_left(&mut self) {
-            if self.left.is_empty() {
-                self.wrap_around();
-            }
-
-            self.right.push_front(self.left.pop_back().unwrap());
+        pub fn current_marble(&self) -> usize {
+            self.circle.current_marble()
         }
     }
 }
 
-fn marble_game_outcome(num_players: usize, last_marble: usize) -> usize {
-    let mut marble_game = game::MarbleGame::new(num_players, last_marble);
-    let mut scores = vec![0; num_players];
+/// Everything that happened over the course of a marble game.
+#[derive(Debug, PartialEq)]
+pub struct GameOutcome {
+    /// Each player's final score.
+    pub scores: Vec<usize>,
+    /// The number of turns on which each player scored.
+    pub scoring_turns: Vec<usize>,
+    /// Every turn on which somebody scored, in order.
+    pub scoring_events: Vec<game::ScoringEvent>,
+}
+
+impl GameOutcome {
+    pub fn high_score(&self) -> usize {
+        *self.scores.iter().max().unwrap()
+    }
+
+    /// Returns the indexes of every player who finished with the high score.
+    pub fn winners(&self) -> Vec<usize> {
+        let high_score = self.high_score();
+
+        (0..self.scores.len())
+            .filter(|&player| self.scores[player] == high_score)
+            .collect()
+    }
+}
+
+/// Plays a game with `num_players` players until the marble worth `last_marble` points has been used.
+pub fn play(num_players: usize, last_marble: usize, rules: game::Rules) -> GameOutcome {
+    play_with_circle::<game::RingCircle>(num_players, last_marble, rules)
+}
+
+/// Like `play`, but keeps the marbles in a `C`.
+pub fn play_with_circle<C: game::Circle>(num_players: usize, last_marble: usize, rules: game::Rules) -> GameOutcome {
+    let mut marble_game = game::MarbleGame::<C>::with_circle(num_players, last_marble, rules);
+    let mut outcome = GameOutcome {
+        scores: vec![0; num_players],
+        scoring_turns: vec![0; num_players],
+        scoring_events: vec![],
+    };
 
     for _ in 0..last_marble {
-        if let Some((player_index, score)) = marble_game.add_marble() {
-            scores[player_index] += score;
+        if let Some(event) = marble_game.add_marble() {
+            outcome.scores[event.player] += event.points();
+            outcome.scoring_turns[event.player] += 1;
+            outcome.scoring_events.push(event);
         }
     }
 
-    *scores.iter().max().unwrap()
+    outcome
+}
+
+fn marble_game_outcome(num_players: usize, last_marble: usize) -> usize {
+    play(num_players, last_marble, game::Rules::default()).high_score()
+}
+
+/// Returns (number of players, value of the last marble).
+fn parse_input() -> (usize, usize) {
+    let contents = fs::read_to_string("src/inputs/9.txt").unwrap();
+    let sentence = contents.trim();
+    scan!("{} players; last marble is worth {} points" <- sentence).unwrap()
 }
 
+/// What is the winning Elf's score?
 pub fn nine_a() -> usize {
-    marble_game_outcome(413, 71082)
+    let (num_players, last_marble) = parse_input();
+    marble_game_outcome(num_players, last_marble)
 }
 
+/// What would the new winning Elf's score be if the number of the last marble were 100 times larger?
 pub fn nine_b() -> usize {
-    marble_game_outcome(413, 7108200)
+    let (num_players, last_marble) = parse_input();
+    marble_game_outcome(num_players, last_marble * 100)
 }
 
 #[cfg(test)]
@@ -126,4 +364,212 @@ mod test {
         assert_eq!(marble_game_outcome(21, 6111), 54718);
         assert_eq!(marble_game_outcome(30, 5807), 37305);
     }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(parse_input(), (413, 71082));
+    }
+
+    #[test]
+    fn test_circle() {
+        // The example game from the puzzle's writeup, one line per turn.
+        // Each line shows the current player, then the circle, with the current marble in parentheses.
+        let example = "
+[-] (0)
+[1]  0 (1)
+[2]  0 (2) 1
+[3]  0  2  1 (3)
+[4]  0 (4) 2  1  3
+[5]  0  4  2 (5) 1  3
+[6]  0  4  2  5  1 (6) 3
+[7]  0  4  2  5  1  6  3 (7)
+[8]  0 (8) 4  2  5  1  6  3  7
+[9]  0  8  4 (9) 2  5  1  6  3  7
+[1]  0  8  4  9  2(10) 5  1  6  3  7
+[2]  0  8  4  9  2 10  5(11) 1  6  3  7
+[3]  0  8  4  9  2 10  5 11  1(12) 6  3  7
+[4]  0  8  4  9  2 10  5 11  1 12  6(13) 3  7
+[5]  0  8  4  9  2 10  5 11  1 12  6 13  3(14) 7
+[6]  0  8  4  9  2 10  5 11  1 12  6 13  3 14  7(15)
+[7]  0(16) 8  4  9  2 10  5 11  1 12  6 13  3 14  7 15
+[8]  0 16  8(17) 4  9  2 10  5 11  1 12  6 13  3 14  7 15
+[9]  0 16  8 17  4(18) 9  2 10  5 11  1 12  6 13  3 14  7 15
+[1]  0 16  8 17  4 18  9(19) 2 10  5 11  1 12  6 13  3 14  7 15
+[2]  0 16  8 17  4 18  9 19  2(20)10  5 11  1 12  6 13  3 14  7 15
+[3]  0 16  8 17  4 18  9 19  2 20 10(21) 5 11  1 12  6 13  3 14  7 15
+[4]  0 16  8 17  4 18  9 19  2 20 10 21  5(22)11  1 12  6 13  3 14  7 15
+[5]  0 16  8 17  4 18(19) 2 20 10 21  5 22 11  1 12  6 13  3 14  7 15
+[6]  0 16  8 17  4 18 19  2(24)20 10 21  5 22 11  1 12  6 13  3 14  7 15
+[7]  0 16  8 17  4 18 19  2 24 20(25)10 21  5 22 11  1 12  6 13  3 14  7 15";
+
+        let mut marble_game = game::MarbleGame::new(9, 25, game::Rules::default());
+
+        for (turn, line) in example.trim().lines().enumerate() {
+            if turn > 0 {
+                let score = marble_game.add_marble();
+                let expected_score = if turn == 23 {
+                    Some(game::ScoringEvent {
+                        player: 4,
+                        marble: 23,
+                        removed_marble: 9,
+                    })
+                } else {
+                    None
+                };
+                assert_eq!(score, expected_score);
+            }
+
+            let circle = line[3..]
+                .replace("(", " ")
+                .replace(")", " ")
+                .split_whitespace()
+                .map(|marble| marble.parse().unwrap())
+                .collect::<Vec<usize>>();
+            let current_marble = line.split('(').nth(1).unwrap().split(')').next().unwrap();
+
+            assert_eq!(marble_game.circle(), circle);
+            assert_eq!(marble_game.current_marble(), current_marble.parse::<usize>().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = game::Rules {
+            scoring_multiple: 5,
+            steps_back: 2,
+        };
+        let mut marble_game = game::MarbleGame::new(3, 6, rules);
+
+        for _ in 0..4 {
+            assert_eq!(marble_game.add_marble(), None);
+        }
+        assert_eq!(marble_game.circle(), vec![0, 4, 2, 1, 3]);
+
+        // Marble 5 scores, along with marble 3, which is two steps counter-clockwise of marble 4.
+        assert_eq!(
+            marble_game.add_marble().map(|event| (event.player, event.points())),
+            Some((1, 8))
+        );
+        assert_eq!(marble_game.circle(), vec![0, 4, 2, 1]);
+        assert_eq!(marble_game.current_marble(), 0);
+
+        let outcome = play(
+            10,
+            1618,
+            game::Rules {
+                scoring_multiple: 10_000,
+                steps_back: 7,
+            },
+        );
+        assert_eq!(outcome.high_score(), 0);
+        assert!(outcome.scoring_events.is_empty());
+    }
+
+    #[test]
+    fn test_marble_zero_removed() {
+        let rules = game::Rules {
+            scoring_multiple: 2,
+            steps_back: 1,
+        };
+        let mut marble_game = game::MarbleGame::new(2, 5, rules);
+
+        // Marble 2 scores, along with marble 0, which is one step counter-clockwise of marble 1.
+        assert_eq!(marble_game.add_marble(), None);
+        assert_eq!(marble_game.add_marble().map(|event| event.removed_marble), Some(0));
+        assert_eq!(marble_game.circle(), vec![1]);
+        assert_eq!(marble_game.current_marble(), 1);
+
+        assert_eq!(marble_game.add_marble(), None);
+        assert_eq!(marble_game.circle(), vec![3, 1]);
+        assert_eq!(marble_game.current_marble(), 3);
+
+        assert_eq!(
+            play_with_circle::<game::DequeCircle>(2, 50, rules),
+            play_with_circle::<game::RingCircle>(2, 50, rules)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "scoring_multiple must be at least 2")]
+    fn test_rules_that_empty_the_circle() {
+        play(
+            2,
+            5,
+            game::Rules {
+                scoring_multiple: 1,
+                steps_back: 0,
+            },
+        );
+    }
+
+    #[test]
+    fn test_play() {
+        let outcome = play(9, 25, game::Rules::default());
+
+        assert_eq!(outcome.scores, vec![0, 0, 0, 0, 32, 0, 0, 0, 0]);
+        assert_eq!(outcome.scoring_turns, vec![0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(outcome.winners(), vec![4]);
+        assert_eq!(
+            outcome.scoring_events,
+            vec![game::ScoringEvent {
+                player: 4,
+                marble: 23,
+                removed_marble: 9
+            }]
+        );
+
+        // Nobody scores until marble 23, so everybody ties.
+        assert_eq!(play(3, 22, game::Rules::default()).winners(), vec![0, 1, 2]);
+
+        let outcome = play(10, 1618, game::Rules::default());
+        assert_eq!(outcome.high_score(), 8317);
+        assert_eq!(outcome.scoring_events.len(), 1618 / 23);
+        assert_eq!(outcome.scoring_turns.iter().sum::<usize>(), 1618 / 23);
+        assert_eq!(
+            outcome.scoring_events.iter().map(|event| event.points()).sum::<usize>(),
+            outcome.scores.iter().sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_circles_agree() {
+        use game::Circle;
+
+        let mut deque = game::DequeCircle::new(0);
+        let mut ring = game::RingCircle::new(0);
+
+        // Rotate by -5 to 5 marbles, then remove a marble every third step and insert one otherwise.
+        for step in 1..300 {
+            let n = (step * 7 % 11) as isize - 5;
+            deque.rotate(n);
+            ring.rotate(n);
+            assert_eq!(deque.marbles(), ring.marbles());
+            assert_eq!(deque.current_marble(), ring.current_marble());
+
+            if step % 3 == 0 {
+                assert_eq!(deque.remove(), ring.remove());
+            } else {
+                deque.insert(step);
+                ring.insert(step);
+            }
+            assert_eq!(deque.marbles(), ring.marbles());
+            assert_eq!(deque.current_marble(), ring.current_marble());
+        }
+
+        for &(num_players, last_marble) in &[(9, 25), (10, 1618), (13, 7999), (30, 5807)] {
+            assert_eq!(
+                play_with_circle::<game::DequeCircle>(num_players, last_marble, game::Rules::default()),
+                play_with_circle::<game::RingCircle>(num_players, last_marble, game::Rules::default())
+            );
+        }
+
+        let rules = game::Rules {
+            scoring_multiple: 5,
+            steps_back: 9,
+        };
+        assert_eq!(
+            play_with_circle::<game::DequeCircle>(4, 300, rules),
+            play_with_circle::<game::RingCircle>(4, 300, rules)
+        );
+    }
 }
@@ -1,159 +1,365 @@
-use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs;
-use std::iter::FromIterator;
-use std::rc::Rc;
 
 use hashbrown::HashMap;
-use hashbrown::HashSet;
-use serde_scan::scan;
-
-const SENTINEL_ROOT_NODE_VALUE: char = '☃';
+use lazy_static::lazy_static;
+use regex::Regex;
 
+/// Step `first` must be finished before step `then` can begin.
 #[derive(Debug, PartialEq)]
-struct StepConstraint {
-    first: char,
-    then: char,
+pub struct StepConstraint {
+    pub first: String,
+    pub then: String,
 }
 
 impl StepConstraint {
     pub fn new(s: &str) -> StepConstraint {
-        let (first, then) = scan!("Step {} must be finished before step {} can begin." <- s).unwrap();
-        StepConstraint { first, then }
+        lazy_static! {
+            static ref PATTERN: Regex =
+                Regex::new(r"^Step (?P<first>\S+) must be finished before step (?P<then>\S+) can begin\.$").unwrap();
+        }
+
+        let caps = PATTERN.captures(s).unwrap();
+        let step = |match_name| caps.name(match_name).unwrap().as_str().to_string();
+
+        StepConstraint {
+            first: step("first"),
+            then: step("then"),
+        }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Node {
-    step: char,
-    children: RefCell<Vec<Rc<Node>>>,
+pub fn load_constraints(path: &str) -> Vec<StepConstraint> {
+    let contents = fs::read_to_string(path).unwrap();
+    contents.lines().map(StepConstraint::new).collect()
 }
 
-impl Node {
-    fn new(step: char) -> Node {
-        Node {
-            step: step,
-            children: RefCell::new(vec![]),
-        }
+/// The steps that make up the sleigh's assembly and the constraints between them.
+/// Each step is identified by its index, which is assigned in the order the steps first appear.
+#[derive(Debug)]
+pub struct DependencyGraph {
+    names: Vec<String>,
+    /// The steps that can't begin until each step is finished.
+    dependents: Vec<Vec<usize>>,
+    /// The steps that must be finished before each step can begin.
+    prerequisites: Vec<Vec<usize>>,
+}
+
+/// A set of constraints that can never all be satisfied.
+/// Each step must be finished before the next can begin, and the last before the first.
+#[derive(Debug, PartialEq)]
+pub struct Cycle {
+    pub steps: Vec<String>,
+}
+
+/// How to color the steps in DependencyGraph::to_dot's output.
+pub enum DotColoring<'a> {
+    /// Leave every step white.
+    Plain,
+    /// Shade steps from blue to red in the order they're completed, and label them with their place in that order.
+    CompletionOrder,
+    /// Highlight the steps on this critical path and the constraints between them.
+    CriticalPath(&'a CriticalPath),
+}
+
+/// When a step could be worked on if there were as many workers as there are steps.
+#[derive(Debug, PartialEq)]
+pub struct StepTiming {
+    pub duration: u32,
+    /// The soonest the step can start, once every step it's waiting on is finished.
+    pub earliest_start: u32,
+    /// The last moment the step can start without holding up the whole assembly.
+    pub latest_start: u32,
+}
+
+impl StepTiming {
+    /// How long the step can be put off without holding up the whole assembly.
+    pub fn slack(&self) -> u32 {
+        self.latest_start - self.earliest_start
     }
 }
 
-fn find_step_in_graph(node: Rc<Node>, step: char) -> Option<Rc<Node>> {
-    if node.step == step {
-        return Some(node);
-    } else {
-        for child in node.children.borrow().iter() {
-            if let Some(ret) = find_step_in_graph(Rc::clone(child), step) {
-                return Some(ret);
+/// The longest chain of steps that each wait on the one before,
+/// which no number of workers can finish any faster than `length` seconds.
+#[derive(Debug)]
+pub struct CriticalPath {
+    /// Indexed by step.
+    pub timings: Vec<StepTiming>,
+    pub path: Vec<usize>,
+    pub length: u32,
+}
+
+impl DependencyGraph {
+    /// Returns an error describing one of the cycles in `constraints` if there are any.
+    pub fn new(constraints: &[StepConstraint]) -> Result<DependencyGraph, Cycle> {
+        let mut graph = DependencyGraph {
+            names: vec![],
+            dependents: vec![],
+            prerequisites: vec![],
+        };
+        let mut indices = HashMap::new();
+
+        for constraint in constraints {
+            let first = graph.index_or_insert(&mut indices, &constraint.first);
+            let then = graph.index_or_insert(&mut indices, &constraint.then);
+
+            // The same constraint might show up more than once, but it's still only one edge.
+            if graph.dependents[first].contains(&then) {
+                continue;
             }
+
+            graph.dependents[first].push(then);
+            graph.prerequisites[then].push(first);
         }
-    }
 
-    None
-}
+        let order = graph.kahn();
+        if order.len() < graph.len() {
+            return Err(graph.find_cycle(&order));
+        }
 
-fn construct_dependency_graph(step_constraints: &[StepConstraint]) -> GraphWalker {
-    // Make a map of step -> [steps that depend on this step].
-    let mut step_parents = HashMap::new();
-    for constraint in step_constraints {
-        let depended_on_by = step_parents.entry(constraint.then).or_insert(vec![]);
-        depended_on_by.push(constraint.first);
-    }
-
-    // Find the nodes that aren't depended on by anything.
-    let mut all_steps = HashSet::new();
-    for constraint in step_constraints {
-        all_steps.insert(constraint.first);
-        all_steps.insert(constraint.then);
-    }
-
-    let steps_with_dependencies = HashSet::from_iter(step_parents.keys().cloned());
-    let steps_with_no_dependencies = all_steps.difference(&steps_with_dependencies);
-
-    let root_node = Rc::new(Node {
-        step: SENTINEL_ROOT_NODE_VALUE,
-        children: RefCell::new(
-            steps_with_no_dependencies
-                .map(|&step| Rc::new(Node::new(step)))
-                .collect(),
-        ),
-    });
-
-    // We've assembled our root node, we're good to go, let's start populating the graph.
-    let mut constraint_deque = VecDeque::from_iter(step_constraints.iter());
-
-    while let Some(constraint) = constraint_deque.pop_front() {
-        if let Some(node_rc) = find_step_in_graph(Rc::clone(&root_node), constraint.first) {
-            // The first step of this constraint has an entry in the dependency graph!
-
-            let child = if let Some(child_rc) = find_step_in_graph(Rc::clone(&root_node), constraint.then) {
-                // The second step of this constraint also has an entry in the graph,
-                // so let's just Rc::clone it and that'll be this constraint's child node.
-                Rc::clone(&child_rc)
-            } else {
-                // The second step of this constraint doesn't have an entry in this graph,
-                // so let's make one.
-                Rc::new(Node::new(constraint.then))
-            };
+        Ok(graph)
+    }
 
-            node_rc.children.borrow_mut().push(child);
-        } else {
-            // Not ready to process this constraint yet, replace it at the end of the queue and try again later.
-            constraint_deque.push_back(constraint);
+    fn index_or_insert(&mut self, indices: &mut HashMap<String, usize>, name: &str) -> usize {
+        if let Some(&index) = indices.get(name) {
+            return index;
         }
+
+        self.names.push(name.to_string());
+        self.dependents.push(vec![]);
+        self.prerequisites.push(vec![]);
+        indices.insert(name.to_string(), self.names.len() - 1);
+
+        self.names.len() - 1
     }
 
-    GraphWalker::new(root_node)
-}
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
 
-struct GraphWalker {
-    buffer: Vec<Rc<Node>>,
-}
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
 
-impl GraphWalker {
-    fn new(root_node: Rc<Node>) -> GraphWalker {
-        let mut walker = GraphWalker {
-            buffer: vec![root_node],
-        };
-        walker.pop_node(SENTINEL_ROOT_NODE_VALUE);
+    pub fn name(&self, step: usize) -> &str {
+        &self.names[step]
+    }
 
-        walker
+    pub fn dependents(&self, step: usize) -> &[usize] {
+        &self.dependents[step]
     }
 
-    fn peek(&self) -> char {
-        self.buffer[0].step
+    pub fn prerequisites(&self, step: usize) -> &[usize] {
+        &self.prerequisites[step]
     }
 
-    fn available_steps(&self) -> Vec<char> {
-        self.buffer.iter().map(|node| node.step).collect()
+    /// Returns the number of steps that must be finished before each step can begin.
+    pub fn in_degrees(&self) -> Vec<usize> {
+        self.prerequisites
+            .iter()
+            .map(|prerequisites| prerequisites.len())
+            .collect()
     }
 
-    fn pop_node(&mut self, step: char) {
-        let index = self.buffer.iter().position(|node| node.step == step).unwrap();
-        let node = self.buffer.remove(index);
+    /// Returns every step in the order they should be completed:
+    /// "If more than one step is ready, choose the step which is first alphabetically."
+    pub fn topological_order(&self) -> Vec<usize> {
+        self.kahn()
+    }
 
-        for child in node.children.borrow().iter() {
-            // If a node's parents have all been processed, then that node's step is ready to go!
-            if Rc::strong_count(child) == 1 {
-                self.buffer.push(Rc::clone(child));
+    /// Kahn's algorithm. Returns fewer than len() steps if the graph has a cycle,
+    /// since none of the steps in the cycle (or after it) ever become ready.
+    fn kahn(&self) -> Vec<usize> {
+        let mut in_degrees = self.in_degrees();
+        let mut ready: BinaryHeap<Reverse<(&str, usize)>> = (0..self.len())
+            .filter(|&step| in_degrees[step] == 0)
+            .map(|step| Reverse((self.name(step), step)))
+            .collect();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(Reverse((_, step))) = ready.pop() {
+            order.push(step);
+
+            for &dependent in self.dependents(step) {
+                in_degrees[dependent] -= 1;
+
+                if in_degrees[dependent] == 0 {
+                    ready.push(Reverse((self.name(dependent), dependent)));
+                }
             }
         }
 
-        // "If more than one step is ready, choose the step which is first alphabetically."
-        self.buffer.sort_by_key(|node| node.step);
+        order
     }
-}
 
-fn dependency_graph_resolution_order(mut walker: GraphWalker) -> String {
-    let mut ret = String::new();
+    /// Finds a cycle among the steps that Kahn's algorithm couldn't put in order.
+    fn find_cycle(&self, order: &[usize]) -> Cycle {
+        let mut ordered = vec![false; self.len()];
+        for &step in order {
+            ordered[step] = true;
+        }
+
+        // Every step that wasn't ordered is waiting on at least one other step that wasn't,
+        // so following those prerequisites backwards must eventually come back around.
+        let mut step = ordered.iter().position(|&ordered| !ordered).unwrap();
+        let mut path = vec![];
+        let mut position_in_path = vec![None; self.len()];
+
+        while position_in_path[step].is_none() {
+            position_in_path[step] = Some(path.len());
+            path.push(step);
+            step = *self
+                .prerequisites(step)
+                .iter()
+                .find(|&&prerequisite| !ordered[prerequisite])
+                .unwrap();
+        }
+
+        let mut cycle = path.split_off(position_in_path[step].unwrap());
+        cycle.reverse();
 
-    while !walker.buffer.is_empty() {
-        let step = walker.peek();
-        ret.push(step);
-        walker.pop_node(step);
+        // Start with whichever step in the cycle appeared first in the input.
+        let first = cycle.iter().enumerate().min_by_key(|&(_, &step)| step).unwrap().0;
+        cycle.rotate_left(first);
+
+        Cycle {
+            steps: cycle.into_iter().map(|step| self.name(step).to_string()).collect(),
+        }
     }
 
-    ret
+    /// Returns a Graphviz description of the graph, with an edge from each step to each of the steps waiting on it.
+    pub fn to_dot(&self, coloring: DotColoring) -> String {
+        fn escape(name: &str) -> String {
+            name.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        fn quote(name: &str) -> String {
+            format!("\"{}\"", escape(name))
+        }
+
+        let mut place_in_order = vec![0; self.len()];
+        for (place, step) in self.topological_order().into_iter().enumerate() {
+            place_in_order[step] = place;
+        }
+
+        let mut lines = vec![
+            "digraph steps {".to_string(),
+            "    node [style=filled, fillcolor=white];".to_string(),
+        ];
+
+        for (step, &place) in place_in_order.iter().enumerate() {
+            let attributes = match coloring {
+                DotColoring::Plain => String::new(),
+                DotColoring::CompletionOrder => {
+                    // Hue runs from 2/3 (blue) for the first step down to 0 (red) for the last.
+                    let progress = place as f64 / (self.len() - 1).max(1) as f64;
+                    format!(
+                        " [label=\"{}\\n{}\", fillcolor=\"{:.3} 0.4 1.0\"]",
+                        escape(self.name(step)),
+                        place + 1,
+                        (1.0 - progress) * 2.0 / 3.0
+                    )
+                }
+                DotColoring::CriticalPath(critical_path) if critical_path.path.contains(&step) => {
+                    " [fillcolor=tomato]".to_string()
+                }
+                DotColoring::CriticalPath(_) => String::new(),
+            };
+
+            lines.push(format!("    {}{};", quote(self.name(step)), attributes));
+        }
+
+        for step in 0..self.len() {
+            for &dependent in self.dependents(step) {
+                let critical = match coloring {
+                    DotColoring::CriticalPath(critical_path) => {
+                        critical_path.path.windows(2).any(|pair| pair == [step, dependent])
+                    }
+                    _ => false,
+                };
+
+                lines.push(format!(
+                    "    {} -> {}{};",
+                    quote(self.name(step)),
+                    quote(self.name(dependent)),
+                    if critical { " [color=tomato, penwidth=2]" } else { "" }
+                ));
+            }
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    /// Works out each step's timings given how long each one takes, along with a critical path.
+    /// If there's more than one critical path, returns the one that's first alphabetically.
+    pub fn critical_path(&self, duration: impl Fn(&str) -> u32) -> CriticalPath {
+        let order = self.topological_order();
+        let durations: Vec<u32> = (0..self.len()).map(|step| duration(self.name(step))).collect();
+
+        // Going forwards, each step can start as soon as the last of its prerequisites is finished...
+        let mut earliest_starts = vec![0; self.len()];
+        for &step in &order {
+            earliest_starts[step] = self
+                .prerequisites(step)
+                .iter()
+                .map(|&prerequisite| earliest_starts[prerequisite] + durations[prerequisite])
+                .max()
+                .unwrap_or(0);
+        }
+
+        let length = (0..self.len())
+            .map(|step| earliest_starts[step] + durations[step])
+            .max()
+            .unwrap_or(0);
+
+        // ...and going backwards, each step has to be finished by the time the first of its dependents must start.
+        let mut latest_starts = vec![0; self.len()];
+        for &step in order.iter().rev() {
+            latest_starts[step] = self
+                .dependents(step)
+                .iter()
+                .map(|&dependent| latest_starts[dependent])
+                .min()
+                .unwrap_or(length)
+                - durations[step];
+        }
+
+        let timings: Vec<StepTiming> = (0..self.len())
+            .map(|step| StepTiming {
+                duration: durations[step],
+                earliest_start: earliest_starts[step],
+                latest_start: latest_starts[step],
+            })
+            .collect();
+
+        // A critical step with no slack is always followed by another critical step that starts
+        // the moment it finishes, except for the last step of the path.
+        let is_next_on_path =
+            |step: usize, start: u32| timings[step].slack() == 0 && timings[step].earliest_start == start;
+        let by_name = |&step: &usize| self.name(step);
+
+        let mut path = vec![];
+        let mut next = (0..self.len())
+            .filter(|&step| is_next_on_path(step, 0))
+            .min_by_key(by_name);
+
+        while let Some(step) = next {
+            path.push(step);
+
+            let finish = timings[step].earliest_start + timings[step].duration;
+            next = self
+                .dependents(step)
+                .iter()
+                .cloned()
+                .filter(|&dependent| is_next_on_path(dependent, finish))
+                .min_by_key(by_name);
+        }
+
+        CriticalPath { timings, path, length }
+    }
 }
 
 /// The instructions specify a series of steps and requirements about
@@ -161,105 +367,327 @@ fn dependency_graph_resolution_order(mut walker: GraphWalker) -> String {
 /// Each step is designated by a single letter.
 /// Your first goal is to determine the order in which the steps should be completed.
 pub fn seven_a() -> String {
-    let contents = fs::read_to_string("src/inputs/7.txt").unwrap();
-    let steps: Vec<StepConstraint> = contents.lines().map(StepConstraint::new).collect();
-    let walker = construct_dependency_graph(&steps);
-    dependency_graph_resolution_order(walker)
+    let graph = DependencyGraph::new(&load_constraints("src/inputs/7.txt")).unwrap();
+
+    graph
+        .topological_order()
+        .into_iter()
+        .map(|step| graph.name(step))
+        .collect()
 }
 
 /// Each step takes 60 seconds plus an amount corresponding to its letter: A=1, B=2, C=3,
 /// and so on. So, step A takes 60+1=61 seconds, while step Z takes 60+26=86 seconds.
-fn step_duration(step: char) -> u32 {
-    61 + u32::from((step as u8) - b'A')
+///
+/// Returns a duration function for Scheduler that works that way for steps named with a single
+/// uppercase letter, with `base_seconds` in place of 60.
+pub fn letter_duration(base_seconds: u32) -> impl Fn(&str) -> u32 {
+    move |step| {
+        assert!(
+            step.len() == 1 && step.as_bytes()[0].is_ascii_uppercase(),
+            "step {:?} isn't a single uppercase letter",
+            step
+        );
+
+        base_seconds + 1 + u32::from(step.as_bytes()[0] - b'A')
+    }
+}
+
+/// "If more than one step is ready, choose the step which is first alphabetically."
+pub fn alphabetical(a: &str, b: &str) -> Ordering {
+    a.cmp(b)
 }
 
 #[derive(Debug)]
 struct ElfJob {
-    step: char,
-    time_left: u32,
+    step: usize,
+    start: u32,
+    end: u32,
 }
 
+/// A pool of helpful elves, each of whom is either idle or working on one job.
 #[derive(Debug)]
 struct ElfPool {
-    num_elves: usize,
-    jobs: Vec<ElfJob>,
+    elves: Vec<Option<ElfJob>>,
 }
 
-/// A pool of helpful elves.
 impl ElfPool {
     fn new(num_elves: usize) -> ElfPool {
         ElfPool {
-            num_elves,
-            jobs: vec![],
+            elves: (0..num_elves).map(|_| None).collect(),
         }
     }
 
-    /// Advance time one second. Return a vector of any steps that were completed during this second.
-    fn advance_time(&mut self) -> Vec<char> {
-        let mut ret = vec![];
-
-        for job in &mut self.jobs {
-            job.time_left -= 1;
-
-            if job.time_left == 0 {
-                ret.push(job.step);
-            }
-        }
-
-        self.jobs.retain(|job| job.time_left > 0);
+    fn is_idle(&self) -> bool {
+        self.elves.iter().all(Option::is_none)
+    }
 
-        ret
+    /// Returns the lowest-numbered elf that isn't working on anything.
+    fn idle_elf(&self) -> Option<usize> {
+        self.elves.iter().position(Option::is_none)
     }
 
     /// Start an elf working on a given step of the sleigh's assembly.
-    fn add_job(&mut self, step: char) {
-        assert_ne!(self.jobs.len(), self.num_elves);
+    fn add_job(&mut self, elf: usize, step: usize, start: u32, duration: u32) {
+        assert!(self.elves[elf].is_none(), "elf {} is already busy", elf);
 
-        self.jobs.push(ElfJob {
+        self.elves[elf] = Some(ElfJob {
             step,
-            time_left: step_duration(step),
+            start,
+            end: start + duration,
         });
     }
 
-    fn steps_in_progress(&self) -> Vec<char> {
-        self.jobs.iter().map(|job| job.step).collect()
+    /// Skips ahead to the moment the next job is finished. Returns that time,
+    /// along with the elves that finished a job then and the jobs they finished.
+    /// Panics if nobody is working on anything.
+    fn advance_to_next_completion(&mut self) -> (u32, Vec<(usize, ElfJob)>) {
+        let time = self.elves.iter().flatten().map(|job| job.end).min().unwrap();
+
+        let finished = self
+            .elves
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, job)| job.as_ref().is_some_and(|job| job.end == time))
+            .map(|(elf, job)| (elf, job.take().unwrap()))
+            .collect();
+
+        (time, finished)
     }
 }
 
-/// Now, you need to account for multiple people working on steps simultaneously.
-/// If multiple steps are available, workers should still begin them in alphabetical order.
-pub fn seven_b() -> i32 {
-    let contents = fs::read_to_string("src/inputs/7.txt").unwrap();
-    let steps: Vec<StepConstraint> = contents.lines().map(StepConstraint::new).collect();
-    let mut walker = construct_dependency_graph(&steps);
-
-    let mut pool = ElfPool::new(5);
-    let mut seconds = 0;
-
-    // While the sleigh is not yet put together:
-    while !walker.buffer.is_empty() || !pool.jobs.is_empty() {
-        // Figure out which steps are available but aren't yet being worked on.
-        let all_available_steps: HashSet<char> = HashSet::from_iter(walker.available_steps());
-        let steps_in_progress = HashSet::from_iter(pool.steps_in_progress());
-        let steps_not_being_worked_on = all_available_steps.difference(&steps_in_progress);
-
-        // Add jobs until all of the elves are busy or we can't add more jobs.
-        for &step in steps_not_being_worked_on {
-            if pool.jobs.len() < pool.num_elves {
-                pool.add_job(step);
+/// One worker's stint on one step, from `start` up to but not including `end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pub step: usize,
+    pub worker: usize,
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Who worked on what, and when.
+#[derive(Debug)]
+pub struct Schedule {
+    pub num_workers: usize,
+    /// Sorted by the time each step was finished.
+    pub assignments: Vec<Assignment>,
+    pub total_seconds: u32,
+}
+
+impl Schedule {
+    fn assignment_at(&self, worker: usize, second: u32) -> Option<&Assignment> {
+        self.assignments
+            .iter()
+            .find(|assignment| assignment.worker == worker && assignment.start <= second && second < assignment.end)
+    }
+
+    /// Returns the steps' names run together if they're all single letters, or separated by commas otherwise.
+    fn join_names(graph: &DependencyGraph, steps: impl Iterator<Item = usize>) -> String {
+        let separator = if (0..graph.len()).all(|step| graph.name(step).chars().count() == 1) {
+            ""
+        } else {
+            ","
+        };
+
+        steps
+            .map(|step| graph.name(step))
+            .collect::<Vec<&str>>()
+            .join(separator)
+    }
+
+    /// Renders the schedule the way the puzzle does, with a row for each second saying
+    /// what each worker is doing then and which steps have been finished so far:
+    ///
+    /// ```text
+    /// Second   Worker 1   Worker 2   Done
+    ///    0        C          .
+    ///    1        C          .
+    ///    2        C          .
+    ///    3        A          F       C
+    /// ```
+    pub fn table(&self, graph: &DependencyGraph) -> String {
+        // Seconds are right-aligned two characters short of the end of the column, as in the puzzle.
+        let number_width = self.total_seconds.to_string().len();
+        let second_width = "Second".len().max(number_width + 2);
+        let worker_width = (0..graph.len())
+            .map(|step| graph.name(step).chars().count())
+            .chain((1..=self.num_workers).map(|worker| format!("Worker {}", worker).len()))
+            .max()
+            .unwrap();
+
+        let mut lines = vec![];
+
+        let mut header = format!("{:<width$}", "Second", width = second_width);
+        for worker in 1..=self.num_workers {
+            header.push_str(&format!(
+                "   {:<width$}",
+                format!("Worker {}", worker),
+                width = worker_width
+            ));
+        }
+        header.push_str("   Done");
+        lines.push(header);
+
+        for second in 0..=self.total_seconds {
+            let mut line = format!("{:>width$}", second, width = second_width - 2);
+            line.push_str("  ");
+
+            for worker in 0..self.num_workers {
+                let cell = self
+                    .assignment_at(worker, second)
+                    .map_or(".", |assignment| graph.name(assignment.step));
+                line.push_str(&format!("   {:^width$}", cell, width = worker_width));
             }
+
+            let done = self
+                .assignments
+                .iter()
+                .filter(|assignment| assignment.end <= second)
+                .map(|assignment| assignment.step);
+            line.push_str("   ");
+            line.push_str(&Schedule::join_names(graph, done));
+
+            lines.push(line.trim_end().to_string());
+        }
+
+        lines.join("\n")
+    }
+
+    /// Renders the schedule as a Gantt chart with one line per worker and one column per second.
+    /// Each step is drawn as its name followed by enough dashes to fill the time it took,
+    /// cut short if the name is longer than that. Idle seconds are drawn as dots.
+    pub fn gantt(&self, graph: &DependencyGraph) -> String {
+        let label_width = format!("Worker {}", self.num_workers).len();
+
+        (0..self.num_workers)
+            .map(|worker| {
+                let mut chart = vec!['.'; self.total_seconds as usize];
+
+                for assignment in self.assignments.iter().filter(|assignment| assignment.worker == worker) {
+                    let cells = &mut chart[assignment.start as usize..assignment.end as usize];
+                    let label = graph.name(assignment.step).chars().chain(std::iter::repeat('-'));
+
+                    for (cell, c) in cells.iter_mut().zip(label) {
+                        *cell = c;
+                    }
+                }
+
+                format!(
+                    "{:<width$} |{}|",
+                    format!("Worker {}", worker + 1),
+                    chart.into_iter().collect::<String>(),
+                    width = label_width
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Works out how a number of workers should go about finishing every step.
+///
+/// `duration` gives the number of seconds each step takes, which must be at least 1.
+/// Whenever a worker is free and more than one step is ready, the step that `priority`
+/// orders first gets started. If several workers are free, the lowest-numbered one goes first.
+pub struct Scheduler<D, P> {
+    pub num_workers: usize,
+    pub duration: D,
+    pub priority: P,
+}
+
+impl<D, P> Scheduler<D, P>
+where
+    D: Fn(&str) -> u32,
+    P: Fn(&str, &str) -> Ordering,
+{
+    pub fn new(num_workers: usize, duration: D, priority: P) -> Self {
+        assert!(num_workers > 0, "can't schedule anything without any workers");
+
+        Scheduler {
+            num_workers,
+            duration,
+            priority,
         }
+    }
+
+    /// Returns the number of seconds it takes to finish every step in `graph`.
+    pub fn run(&self, graph: &DependencyGraph) -> u32 {
+        self.schedule(graph).total_seconds
+    }
+
+    pub fn schedule(&self, graph: &DependencyGraph) -> Schedule {
+        // How many unfinished steps each step is still waiting on.
+        let mut prerequisites_left = graph.in_degrees();
+        let mut available_steps: Vec<usize> = (0..graph.len()).filter(|&step| prerequisites_left[step] == 0).collect();
+
+        let mut pool = ElfPool::new(self.num_workers);
+        let mut assignments = vec![];
+        let mut seconds = 0;
+
+        // While the sleigh is not yet put together:
+        while !available_steps.is_empty() || !pool.is_idle() {
+            // Add jobs until all of the elves are busy or we can't add more jobs.
+            available_steps.sort_by(|&a, &b| (self.priority)(graph.name(a), graph.name(b)));
+
+            while let Some(elf) = pool.idle_elf() {
+                if available_steps.is_empty() {
+                    break;
+                }
+
+                let step = available_steps.remove(0);
+                let duration = (self.duration)(graph.name(step));
+                assert!(duration > 0, "step {} must take at least one second", graph.name(step));
+
+                pool.add_job(elf, step, seconds, duration);
+            }
 
-        // Advance time one second and see if any jobs are done.
-        let done_steps = pool.advance_time();
-        for step in done_steps {
-            walker.pop_node(step);
+            // Nothing can happen until somebody finishes their job.
+            let (time, finished) = pool.advance_to_next_completion();
+            seconds = time;
+
+            for (elf, job) in finished {
+                for &dependent in graph.dependents(job.step) {
+                    prerequisites_left[dependent] -= 1;
+
+                    if prerequisites_left[dependent] == 0 {
+                        available_steps.push(dependent);
+                    }
+                }
+
+                assignments.push(Assignment {
+                    step: job.step,
+                    worker: elf,
+                    start: job.start,
+                    end: job.end,
+                });
+            }
         }
 
-        seconds += 1;
+        Schedule {
+            num_workers: self.num_workers,
+            assignments,
+            total_seconds: seconds,
+        }
     }
+}
+
+/// Now, you need to account for multiple people working on steps simultaneously.
+/// If multiple steps are available, workers should still begin them in alphabetical order.
+pub fn seven_b() -> u32 {
+    let graph = DependencyGraph::new(&load_constraints("src/inputs/7.txt")).unwrap();
+    Scheduler::new(5, letter_duration(60), alphabetical).run(&graph)
+}
+
+/// Returns the least number of seconds the assembly could take with any number of workers,
+/// along with the steps on the critical path that makes it take that long.
+pub fn seven_b_lower_bound() -> (u32, String) {
+    let graph = DependencyGraph::new(&load_constraints("src/inputs/7.txt")).unwrap();
+    let critical_path = graph.critical_path(letter_duration(60));
 
-    seconds
+    (
+        critical_path.length,
+        critical_path.path.iter().map(|&step| graph.name(step)).collect(),
+    )
 }
 
 #[cfg(test)]
@@ -270,19 +698,256 @@ mod test {
     fn test_solution() {
         assert_eq!(seven_a(), "ABGKCMVWYDEHFOPQUILSTNZRJX");
         assert_eq!(seven_b(), 898);
+
+        let (lower_bound, path) = seven_b_lower_bound();
+        // Five workers are enough that the assembly is never held up waiting for one.
+        assert_eq!(lower_bound, 898);
+        assert_eq!(path, "AMEOULSNZRJX");
+        assert_eq!(
+            path.chars()
+                .map(|step| letter_duration(60)(&step.to_string()))
+                .sum::<u32>(),
+            lower_bound
+        );
     }
 
     #[test]
     fn test_step_constraint_new() {
         assert_eq!(
             StepConstraint::new("Step C must be finished before step A can begin."),
-            StepConstraint { first: 'C', then: 'A' }
+            StepConstraint {
+                first: "C".to_string(),
+                then: "A".to_string()
+            }
         )
     }
 
     #[test]
-    fn test_step_duration() {
-        assert_eq!(step_duration('A'), 61);
-        assert_eq!(step_duration('Z'), 86);
+    fn test_letter_duration() {
+        assert_eq!(letter_duration(60)("A"), 61);
+        assert_eq!(letter_duration(60)("Z"), 86);
+        assert_eq!(letter_duration(0)("C"), 3);
+    }
+
+    fn graph_of(lines: &[&str]) -> Result<DependencyGraph, Cycle> {
+        let steps: Vec<StepConstraint> = lines.iter().map(|line| StepConstraint::new(line)).collect();
+        DependencyGraph::new(&steps)
+    }
+
+    fn sample_graph() -> DependencyGraph {
+        DependencyGraph::new(&load_constraints("src/inputs/7_sample.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let graph = sample_graph();
+        let order: String = graph
+            .topological_order()
+            .into_iter()
+            .map(|step| graph.name(step))
+            .collect();
+
+        assert_eq!(order, "CABDFE");
+        assert_eq!(graph.len(), 6);
+        // Steps are numbered in the order they first appear: C, A, F, B, D, E.
+        assert_eq!(graph.in_degrees(), vec![0, 1, 1, 1, 1, 3]);
+    }
+
+    #[test]
+    fn test_duplicate_constraints() {
+        let constraint = "Step A must be finished before step B can begin.";
+        let graph = graph_of(&[constraint, constraint]).unwrap();
+
+        assert_eq!(graph.in_degrees(), vec![0, 1]);
+        assert_eq!(graph.prerequisites(1), &[0]);
+        assert_eq!(graph.dependents(0), &[1]);
+        assert_eq!(graph.to_dot(DotColoring::Plain).matches("\"A\" -> \"B\"").count(), 1);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let cycle = graph_of(&[
+            "Step A must be finished before step B can begin.",
+            "Step B must be finished before step C can begin.",
+            "Step C must be finished before step D can begin.",
+            "Step D must be finished before step B can begin.",
+            "Step D must be finished before step E can begin.",
+        ])
+        .unwrap_err();
+
+        assert_eq!(cycle.steps, vec!["B", "C", "D"]);
+
+        let cycle = graph_of(&["Step A must be finished before step A can begin."]).unwrap_err();
+        assert_eq!(cycle.steps, vec!["A"]);
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let graph = sample_graph();
+
+        assert_eq!(Scheduler::new(2, letter_duration(0), alphabetical).run(&graph), 15);
+        assert_eq!(Scheduler::new(1, letter_duration(0), alphabetical).run(&graph), 21);
+
+        // A, B and Z are all ready at the start, but Z is on the way to Y, the longest step.
+        let graph = graph_of(&[
+            "Step A must be finished before step Y can begin.",
+            "Step B must be finished before step Y can begin.",
+            "Step Z must be finished before step Y can begin.",
+        ])
+        .unwrap();
+        let reverse_alphabetical = |a: &str, b: &str| b.cmp(a);
+
+        assert_eq!(Scheduler::new(2, letter_duration(0), alphabetical).run(&graph), 52);
+        assert_eq!(
+            Scheduler::new(2, letter_duration(0), reverse_alphabetical).run(&graph),
+            51
+        );
+    }
+
+    #[test]
+    fn test_scheduler_named_steps() {
+        let graph = graph_of(&[
+            "Step fetch must be finished before step build can begin.",
+            "Step build must be finished before step test can begin.",
+            "Step build must be finished before step package can begin.",
+            "Step lint must be finished before step package can begin.",
+        ])
+        .unwrap();
+
+        let durations: HashMap<&str, u32> = [("fetch", 5), ("build", 10), ("lint", 20), ("test", 30), ("package", 2)]
+            .iter()
+            .cloned()
+            .collect();
+        let duration = |step: &str| durations[step];
+
+        assert_eq!(Scheduler::new(1, duration, alphabetical).run(&graph), 67);
+        // lint runs alongside fetch and build, and package alongside test.
+        assert_eq!(Scheduler::new(2, duration, alphabetical).run(&graph), 45);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let graph = sample_graph();
+        let schedule = Scheduler::new(2, letter_duration(0), alphabetical).schedule(&graph);
+
+        assert_eq!(
+            schedule.table(&graph),
+            [
+                "Second   Worker 1   Worker 2   Done",
+                "   0        C          .",
+                "   1        C          .",
+                "   2        C          .",
+                "   3        A          F       C",
+                "   4        B          F       CA",
+                "   5        B          F       CA",
+                "   6        D          F       CAB",
+                "   7        D          F       CAB",
+                "   8        D          F       CAB",
+                "   9        D          .       CABF",
+                "  10        E          .       CABFD",
+                "  11        E          .       CABFD",
+                "  12        E          .       CABFD",
+                "  13        E          .       CABFD",
+                "  14        E          .       CABFD",
+                "  15        .          .       CABFDE",
+            ]
+            .join("\n")
+        );
+
+        assert_eq!(
+            schedule.gantt(&graph),
+            ["Worker 1 |C--AB-D---E----|", "Worker 2 |...F-----......|"].join("\n")
+        );
+    }
+
+    #[test]
+    fn test_schedule_long_table() {
+        let graph = graph_of(&["Step A must be finished before step B can begin."]).unwrap();
+        let schedule =
+            Scheduler::new(1, |step: &str| if step == "A" { 10_000 } else { 3 }, alphabetical).schedule(&graph);
+        let table = schedule.table(&graph);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(schedule.total_seconds, 10_003);
+        assert_eq!(lines[0], "Second    Worker 1   Done");
+        assert_eq!(lines[1], "    0        A");
+        assert_eq!(lines[9999], " 9998        A");
+        assert_eq!(lines[10001], "10000        B       A");
+        assert_eq!(lines[10004], "10003        .       AB");
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let graph = sample_graph();
+        let critical_path = graph.critical_path(letter_duration(0));
+
+        // C-F-E takes 3 + 6 + 5 seconds, against 13 for C-A-D-E and 11 for C-A-B-E.
+        assert_eq!(critical_path.length, 14);
+        let path: String = critical_path.path.iter().map(|&step| graph.name(step)).collect();
+        assert_eq!(path, "CFE");
+
+        let slack: HashMap<&str, u32> = (0..graph.len())
+            .map(|step| (graph.name(step), critical_path.timings[step].slack()))
+            .collect();
+        let expected: HashMap<&str, u32> = [("C", 0), ("A", 1), ("B", 3), ("D", 1), ("F", 0), ("E", 0)]
+            .iter()
+            .cloned()
+            .collect();
+
+        assert_eq!(slack, expected);
+        assert_eq!(
+            critical_path.timings[graph.topological_order()[1]],
+            StepTiming {
+                duration: 1,
+                earliest_start: 3,
+                latest_start: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let graph = sample_graph();
+        let edges = [
+            "    \"C\" -> \"A\";",
+            "    \"C\" -> \"F\";",
+            "    \"A\" -> \"B\";",
+            "    \"A\" -> \"D\";",
+            "    \"F\" -> \"E\";",
+            "    \"B\" -> \"E\";",
+            "    \"D\" -> \"E\";",
+        ];
+
+        let mut expected = vec!["digraph steps {", "    node [style=filled, fillcolor=white];"];
+        expected.extend(&[
+            "    \"C\";",
+            "    \"A\";",
+            "    \"F\";",
+            "    \"B\";",
+            "    \"D\";",
+            "    \"E\";",
+        ]);
+        expected.extend(&edges);
+        expected.push("}");
+        assert_eq!(graph.to_dot(DotColoring::Plain), expected.join("\n"));
+
+        let dot = graph.to_dot(DotColoring::CompletionOrder);
+        assert!(dot.contains("    \"C\" [label=\"C\\n1\", fillcolor=\"0.667 0.4 1.0\"];"));
+        assert!(dot.contains("    \"E\" [label=\"E\\n6\", fillcolor=\"0.000 0.4 1.0\"];"));
+
+        let critical_path = graph.critical_path(letter_duration(0));
+        let dot = graph.to_dot(DotColoring::CriticalPath(&critical_path));
+        assert!(dot.contains("    \"F\" [fillcolor=tomato];"));
+        assert!(dot.contains("    \"A\";"));
+        assert!(dot.contains("    \"C\" -> \"F\" [color=tomato, penwidth=2];"));
+        assert!(dot.contains("    \"C\" -> \"A\";"));
+    }
+
+    #[test]
+    fn test_to_dot_quoting() {
+        let graph = graph_of(&["Step say\"hi\" must be finished before step back\\slash can begin."]).unwrap();
+        assert!(graph
+            .to_dot(DotColoring::Plain)
+            .contains("    \"say\\\"hi\\\"\" -> \"back\\\\slash\";"));
     }
 }
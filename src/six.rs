@@ -1,141 +1,545 @@
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::needless_range_loop))]
-
 use std::fs;
+use std::io;
 
 use hashbrown::HashSet;
+use itertools::iproduct;
 use itertools::Itertools;
 use serde_scan::scan;
 
-use crate::util;
-
-const SENTINEL_LOCATION_ID: i32 = -1;
+use crate::util::{self, Rgb};
 
 /// Using only the Manhattan distance, determine the area around each coordinate
 /// by counting the number of integer X,Y locations that are closest to that coordinate
 /// (and aren't tied in distance to any other coordinate).
-
-#[derive(Debug)]
-struct Location {
-    id: i32,
-    x: usize,
-    y: usize,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub x: i32,
+    pub y: i32,
 }
 
 /// "The sum of the absolute values of the differences of the coordinates",
 /// according to math stackexchange.
-fn manhattan_distance(x1: usize, y1: usize, x2: usize, y2: usize) -> u32 {
-    ((x1 as i32 - x2 as i32).abs() + (y1 as i32 - y2 as i32).abs()) as u32
+fn manhattan_distance(x1: i32, y1: i32, x2: i32, y2: i32) -> u32 {
+    ((x1 - x2).abs() + (y1 - y2).abs()) as u32
 }
 
-fn load_locations() -> Vec<Location> {
-    let contents = fs::read_to_string("src/inputs/6.txt").unwrap();
-    let mut id = 0;
-    let mut locations = Vec::new();
+pub fn load_locations(path: &str) -> Vec<Location> {
+    let contents = fs::read_to_string(path).unwrap();
 
-    for line in contents.lines() {
-        let (x, y) = scan!("{}, {}" <- line).unwrap();
-        locations.push(Location { id, x, y });
-        id += 1;
-    }
-
-    locations
+    contents
+        .lines()
+        .map(|line| {
+            let (x, y) = scan!("{}, {}" <- line).unwrap();
+            Location { x, y }
+        })
+        .collect()
 }
 
-struct LocationGrid {
-    grid: Vec<Vec<i32>>,
-    min_x: usize,
-    min_y: usize,
-    max_x: usize,
-    max_y: usize,
+/// A rectangle of cells, edges included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min_x: i32,
+    pub max_x: i32,
+    pub min_y: i32,
+    pub max_y: i32,
 }
 
-/// Returns a LocationGrid whose values are all SENTINEL_LOCATION_ID.
-fn initialize_grid(locations: &[Location]) -> LocationGrid {
-    let (min_x, max_x) = locations
-        .iter()
-        .map(|location| location.x)
-        .minmax()
-        .into_option()
-        .unwrap();
-    let (min_y, max_y) = locations
-        .iter()
-        .map(|location| location.y)
-        .minmax()
-        .into_option()
-        .unwrap();
+impl Bounds {
+    /// Returns the smallest rectangle containing every location.
+    pub fn of(locations: &[Location]) -> Bounds {
+        let (min_x, max_x) = locations
+            .iter()
+            .map(|location| location.x)
+            .minmax()
+            .into_option()
+            .unwrap();
+        let (min_y, max_y) = locations
+            .iter()
+            .map(|location| location.y)
+            .minmax()
+            .into_option()
+            .unwrap();
+
+        Bounds {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+        }
+    }
 
-    let grid = vec![vec![SENTINEL_LOCATION_ID; max_y as usize]; max_x as usize];
+    pub fn width(&self) -> usize {
+        (self.max_x - self.min_x + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max_y - self.min_y + 1) as usize
+    }
 
-    LocationGrid {
-        grid,
-        min_x,
-        min_y,
-        max_x,
-        max_y,
+    /// Returns a grid with a value for each cell in the rectangle, indexed by
+    /// [x - min_x][y - min_y].
+    pub fn map<T>(&self, mut f: impl FnMut(i32, i32) -> T) -> Vec<Vec<T>> {
+        (self.min_x..=self.max_x)
+            .map(|x| (self.min_y..=self.max_y).map(|y| f(x, y)).collect())
+            .collect()
     }
 }
 
-/// What is the size of the largest area that isn't infinite?
-pub fn six_a() -> u32 {
-    let locations = load_locations();
+pub mod voronoi {
+    use itertools::iproduct;
 
-    let mut location_grid = initialize_grid(&locations);
+    use super::{Bounds, Location};
 
-    let sentinel_location = Location {
-        id: SENTINEL_LOCATION_ID,
-        x: 0,
-        y: 0,
-    };
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Metric {
+        Manhattan,
+        Chebyshev,
+        Euclidean,
+    }
 
-    // Calculate the ID of the closest location to each spot on the grid.
+    impl Metric {
+        /// Euclidean distances are squared, which orders them the same way without any rounding.
+        pub fn distance(self, a: Location, b: Location) -> i64 {
+            let dx = (i64::from(a.x) - i64::from(b.x)).abs();
+            let dy = (i64::from(a.y) - i64::from(b.y)).abs();
 
-    for x in location_grid.min_x..location_grid.max_x {
-        for y in location_grid.min_y..location_grid.max_y {
-            let mut closest_location = &locations[0];
-            let mut smallest_distance = std::u32::MAX;
+            match self {
+                Metric::Manhattan => dx + dy,
+                Metric::Chebyshev => dx.max(dy),
+                Metric::Euclidean => dx * dx + dy * dy,
+            }
+        }
+    }
 
-            for location in &locations {
-                let distance = manhattan_distance(location.x, location.y, x, y);
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Area {
+        Finite(usize),
+        Infinite,
+    }
 
-                if distance < smallest_distance {
-                    smallest_distance = distance;
-                    closest_location = location;
-                } else if distance == smallest_distance {
-                    closest_location = &sentinel_location;
-                }
+    /// Returns the index of the location closest to (x, y), or None if more than one is closest.
+    pub fn closest(locations: &[Location], metric: Metric, x: i32, y: i32) -> Option<usize> {
+        let cell = Location { x, y };
+        let mut closest = None;
+        let mut smallest_distance = i64::MAX;
+
+        for (i, &location) in locations.iter().enumerate() {
+            let distance = metric.distance(location, cell);
+
+            if distance < smallest_distance {
+                smallest_distance = distance;
+                closest = Some(i);
+            } else if distance == smallest_distance {
+                closest = None;
+            }
+        }
+
+        closest
+    }
+
+    /// Returns the number of cells closest to each location, in the same order as `locations`.
+    pub fn areas(locations: &[Location], metric: Metric) -> Vec<Area> {
+        match metric {
+            Metric::Manhattan | Metric::Chebyshev => grid_areas(locations, metric),
+            Metric::Euclidean => euclidean_areas(locations),
+        }
+    }
+
+    /// The letter for the location at `index` on maps: 'a' for the first, 'b' for the second,
+    /// and so on, starting over at 'a' after 'z'.
+    pub fn label(index: usize) -> char {
+        (b'a' + (index % 26) as u8) as char
+    }
+
+    /// Draws the cells in `bounds` the way the puzzle does, with each cell marked with the letter of
+    /// the location closest to it, in uppercase on the location itself, or '.' if it's tied.
+    pub fn render(locations: &[Location], metric: Metric, bounds: Bounds) -> String {
+        (bounds.min_y..=bounds.max_y)
+            .map(|y| {
+                (bounds.min_x..=bounds.max_x)
+                    .map(|x| match closest(locations, metric, x, y) {
+                        Some(i) if locations[i] == (Location { x, y }) => label(i).to_ascii_uppercase(),
+                        Some(i) => label(i),
+                        None => '.',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Manhattan distance, and Chebyshev distance once the grid is turned 45 degrees, both have
+    /// a handy property: once a cell is past the locations' bounds, stepping further away
+    /// (straight out from an edge, or diagonally out from a corner) adds the same amount to its
+    /// distance from every location, so it's still closest to whichever location it was before.
+    ///
+    /// So a location's area is infinite exactly when it's closest to a cell on or past the edge
+    /// of those bounds, and every finite area fits inside them.
+    fn grid_areas(locations: &[Location], metric: Metric) -> Vec<Area> {
+        // For Chebyshev distance, u = x + y and v = x - y. Only cells whose u and v are both odd
+        // or both even exist, so stepping away moves u or v by 2 at a time, and a cell that's one
+        // past the bounds might be the closest cell to them.
+        let frame = |x: i32, y: i32| match metric {
+            Metric::Chebyshev => (x + y, x - y),
+            _ => (x, y),
+        };
+        let frame_bounds = Bounds::of(
+            &locations
+                .iter()
+                .map(|location| {
+                    let (u, v) = frame(location.x, location.y);
+                    Location { x: u, y: v }
+                })
+                .collect::<Vec<Location>>(),
+        );
+
+        // The cells to check, in the usual coordinates.
+        let bounds = match metric {
+            Metric::Chebyshev => Bounds {
+                min_x: (frame_bounds.min_x + frame_bounds.min_y).div_euclid(2) - 1,
+                max_x: (frame_bounds.max_x + frame_bounds.max_y).div_euclid(2) + 2,
+                min_y: (frame_bounds.min_x - frame_bounds.max_y).div_euclid(2) - 1,
+                max_y: (frame_bounds.max_x - frame_bounds.min_y).div_euclid(2) + 2,
+            },
+            _ => frame_bounds,
+        };
+
+        let mut areas = vec![Area::Finite(0); locations.len()];
+
+        for (x, y) in iproduct!(bounds.min_x..=bounds.max_x, bounds.min_y..=bounds.max_y) {
+            let (u, v) = frame(x, y);
+            if u < frame_bounds.min_x - 1
+                || u > frame_bounds.max_x + 1
+                || v < frame_bounds.min_y - 1
+                || v > frame_bounds.max_y + 1
+            {
+                continue;
             }
 
-            location_grid.grid[x as usize][y as usize] = closest_location.id;
+            if let Some(closest) = closest(locations, metric, x, y) {
+                let on_or_past_edge = u <= frame_bounds.min_x
+                    || u >= frame_bounds.max_x
+                    || v <= frame_bounds.min_y
+                    || v >= frame_bounds.max_y;
+
+                areas[closest] = match areas[closest] {
+                    _ if on_or_past_edge => Area::Infinite,
+                    Area::Finite(area) => Area::Finite(area + 1),
+                    Area::Infinite => Area::Infinite,
+                };
+            }
         }
+
+        areas
+    }
+
+    /// A cell P is closer to location s than to location t when |P - s|² < |P - t|², which works
+    /// out to 2P·(s - t) > |s|² - |t|². So each location's cells are the lattice points inside
+    /// a convex polygon, possibly an unbounded one, and there's no grid that's sure to hold them.
+    fn euclidean_areas(locations: &[Location]) -> Vec<Area> {
+        let point = |location: &Location| (i64::from(location.x), i64::from(location.y));
+
+        locations
+            .iter()
+            .map(|s| {
+                let (sx, sy) = point(s);
+                let mut constraints = vec![];
+
+                for t in locations.iter().filter(|&t| !std::ptr::eq(s, t)) {
+                    let (tx, ty) = point(t);
+
+                    if (sx, sy) == (tx, ty) {
+                        // Every cell is tied between the two.
+                        return Area::Finite(0);
+                    }
+
+                    constraints.push(((sx - tx, sy - ty), sx * sx + sy * sy - tx * tx - ty * ty));
+                }
+
+                if is_unbounded(&constraints) {
+                    Area::Infinite
+                } else {
+                    Area::Finite(count_cells((sx, sy), &constraints))
+                }
+            })
+            .collect()
     }
 
-    // If a Location's .id appears on the edge of the grid,
-    // that means that it has the potential to claim an infinitely large area.
+    /// Each constraint ((a, b), c) is 2(a, b)·P > c, for a location whose cells these are.
+    ///
+    /// If there's a direction d with d·(a, b) ≥ 0 for every constraint, then starting from the
+    /// location itself and taking steps of d never breaks any of them, so there are infinitely many
+    /// cells. If not, the polygon is bounded. When such a d exists, one can always be found
+    /// at right angles to one of the constraints' (a, b)s.
+    fn is_unbounded(constraints: &[((i64, i64), i64)]) -> bool {
+        constraints.iter().all(|&((a, b), _)| a == 0 && b == 0)
+            || constraints.iter().any(|&((a, b), _)| {
+                [(-b, a), (b, -a)].iter().any(|&(dx, dy)| {
+                    constraints
+                        .iter()
+                        .all(|&((other_a, other_b), _)| dx * other_a + dy * other_b >= 0)
+                })
+            })
+    }
 
-    let mut infinite_area_location_ids = HashSet::new();
+    /// Counts the cells in a bounded polygon that contains `start`, one row at a time.
+    fn count_cells(start: (i64, i64), constraints: &[((i64, i64), i64)]) -> usize {
+        let mut count = 0;
 
-    for &x in [location_grid.min_x, location_grid.max_x - 1].iter() {
-        for y in location_grid.min_y..location_grid.max_y {
-            infinite_area_location_ids.insert(location_grid.grid[x][y]);
+        // The polygon is convex, so once a row is entirely outside it, so is every row past it.
+        for &step in &[1, -1] {
+            let mut y = if step == 1 { start.1 } else { start.1 - 1 };
+
+            while let Some(row) = row_cells(y, constraints) {
+                count += row;
+                y += step;
+            }
         }
+
+        count
     }
 
-    for &y in [location_grid.min_y, location_grid.max_y - 1].iter() {
-        for x in location_grid.min_x..location_grid.max_x {
-            infinite_area_location_ids.insert(location_grid.grid[x][y]);
+    /// Returns None if no part of row y is inside the polygon,
+    /// or otherwise the number of cells in the row that are.
+    fn row_cells(y: i64, constraints: &[((i64, i64), i64)]) -> Option<usize> {
+        // The row's x has to be more than every (numerator, denominator) in `lower`,
+        // and less than every one in `upper`. Denominators are all positive.
+        let mut lower: Option<(i64, i64)> = None;
+        let mut upper: Option<(i64, i64)> = None;
+        let less_than = |(n1, d1): (i64, i64), (n2, d2): (i64, i64)| {
+            i128::from(n1) * i128::from(d2) < i128::from(n2) * i128::from(d1)
+        };
+
+        for &((a, b), c) in constraints {
+            // 2ax > c - 2by
+            let rest = c - 2 * b * y;
+
+            if a > 0 {
+                let bound = (rest, 2 * a);
+                if lower.is_none_or(|lower| less_than(lower, bound)) {
+                    lower = Some(bound);
+                }
+            } else if a < 0 {
+                let bound = (-rest, -2 * a);
+                if upper.is_none_or(|upper| less_than(bound, upper)) {
+                    upper = Some(bound);
+                }
+            } else if rest >= 0 {
+                return None;
+            }
         }
+
+        // The polygon is bounded, so every row has bounds on both sides.
+        let (lower, upper) = (lower.unwrap(), upper.unwrap());
+        if !less_than(lower, upper) {
+            return None;
+        }
+
+        let first = lower.0.div_euclid(lower.1) + 1;
+        let last = -(-upper.0).div_euclid(upper.1) - 1;
+
+        Some((last - first + 1).max(0) as usize)
     }
+}
 
-    let candidate_spaces = location_grid
-        .grid
+/// What is the size of the largest area that isn't infinite?
+pub fn six_a() -> usize {
+    let locations = load_locations("src/inputs/6.txt");
+
+    voronoi::areas(&locations, voronoi::Metric::Manhattan)
+        .into_iter()
+        .filter_map(|area| match area {
+            voronoi::Area::Finite(area) => Some(area),
+            voronoi::Area::Infinite => None,
+        })
+        .max()
+        .unwrap()
+}
+
+fn total_distance(locations: &[Location], x: i32, y: i32) -> u32 {
+    locations
         .iter()
-        .flatten()
-        .cloned()
-        .filter(|&id| id != SENTINEL_LOCATION_ID && !infinite_area_location_ids.contains(&id));
+        .map(|location| manhattan_distance(location.x, location.y, x, y))
+        .sum()
+}
+
+/// The cells whose total distance to every location is less than some threshold.
+#[derive(Debug)]
+pub struct SafeRegion {
+    pub cells: HashSet<(i32, i32)>,
+    /// Whether every cell can be reached from every other by moving up, down, left and right
+    /// without leaving the region. An empty region counts as connected.
+    pub connected: bool,
+}
+
+/// The total distance along one axis from any coordinate to each of a set of coordinates.
+struct AxisDistances {
+    sorted: Vec<i64>,
+    /// prefix_sums[i] is the sum of the first i coordinates in `sorted`.
+    prefix_sums: Vec<i64>,
+}
 
-    let freqs = util::frequencies(candidate_spaces);
+impl AxisDistances {
+    fn new(coordinates: impl Iterator<Item = i32>) -> AxisDistances {
+        let mut sorted: Vec<i64> = coordinates.map(i64::from).collect();
+        sorted.sort();
 
-    *(freqs.iter().max_by_key(|(_, &count)| count).unwrap().1)
+        let mut prefix_sums = vec![0];
+        for &coordinate in &sorted {
+            prefix_sums.push(prefix_sums.last().unwrap() + coordinate);
+        }
+
+        AxisDistances { sorted, prefix_sums }
+    }
+
+    fn total_distance(&self, coordinate: i32) -> u32 {
+        let coordinate = i64::from(coordinate);
+        let n = self.sorted.len();
+
+        // Every coordinate up to this one is coordinate - c away from it, and every one past it is c - coordinate.
+        let below = self.sorted.partition_point(|&c| c <= coordinate);
+        let below_total = coordinate * below as i64 - self.prefix_sums[below];
+        let above_total = (self.prefix_sums[n] - self.prefix_sums[below]) - coordinate * (n - below) as i64;
+
+        (below_total + above_total) as u32
+    }
+}
+
+/// Returns bounds that are sure to hold every cell whose total distance is less than `threshold`.
+fn search_bounds(locations: &[Location], threshold: u32) -> Bounds {
+    // A cell that's d cells past the edge of the locations' bounds is more than d away from
+    // every location, so its total distance is at least d * locations.len(). That puts every cell
+    // in the region within threshold / locations.len() of the bounds, which might be well past them.
+    // With no locations at all, every cell's total distance is 0, so there'd be no end to the region.
+    assert!(!locations.is_empty(), "a safe region needs at least one location");
+    let margin = (threshold as usize / locations.len()) as i32;
+    let bounds = Bounds::of(locations);
+
+    Bounds {
+        min_x: bounds.min_x - margin,
+        max_x: bounds.max_x + margin,
+        min_y: bounds.min_y - margin,
+        max_y: bounds.max_y + margin,
+    }
+}
+
+fn find_region(bounds: Bounds, threshold: u32, total_distance: impl Fn(i32, i32) -> u32) -> SafeRegion {
+    let cells: HashSet<(i32, i32)> = iproduct!(bounds.min_x..=bounds.max_x, bounds.min_y..=bounds.max_y)
+        .filter(|&(x, y)| total_distance(x, y) < threshold)
+        .collect();
+
+    let connected = match cells.iter().next() {
+        None => true,
+        Some(&start) => {
+            let mut seen = HashSet::new();
+            let mut to_visit = vec![start];
+            seen.insert(start);
+
+            while let Some((x, y)) = to_visit.pop() {
+                for &neighbor in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if cells.contains(&neighbor) && seen.insert(neighbor) {
+                        to_visit.push(neighbor);
+                    }
+                }
+            }
+
+            seen.len() == cells.len()
+        }
+    };
+
+    SafeRegion { cells, connected }
+}
+
+/// Finds every cell whose total distance to all of the locations is less than `threshold`.
+///
+/// A cell's total Manhattan distance is its total distance along the x axis plus its total
+/// distance along the y axis, so rather than adding up the distance to every location for every
+/// cell, this works out each column's and each row's total once, and adds those together.
+///
+/// Panics if there are no locations.
+pub fn safe_region(locations: &[Location], threshold: u32) -> SafeRegion {
+    let bounds = search_bounds(locations, threshold);
+    let x_distances = AxisDistances::new(locations.iter().map(|location| location.x));
+    let y_distances = AxisDistances::new(locations.iter().map(|location| location.y));
+
+    let column_totals: Vec<u32> = (bounds.min_x..=bounds.max_x)
+        .map(|x| x_distances.total_distance(x))
+        .collect();
+    let row_totals: Vec<u32> = (bounds.min_y..=bounds.max_y)
+        .map(|y| y_distances.total_distance(y))
+        .collect();
+
+    find_region(bounds, threshold, |x, y| {
+        column_totals[(x - bounds.min_x) as usize] + row_totals[(y - bounds.min_y) as usize]
+    })
+}
+
+/// Like safe_region, but adds up every cell's distance to every location.
+pub fn safe_region_cell_by_cell(locations: &[Location], threshold: u32) -> SafeRegion {
+    find_region(search_bounds(locations, threshold), threshold, |x, y| {
+        total_distance(locations, x, y)
+    })
+}
+
+/// See https://en.wikipedia.org/wiki/HSL_and_HSV#HSV_to_RGB. All three arguments go from 0 to 1.
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgb {
+    let sector = hue * 6.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+
+    [channel(r), channel(g), channel(b)]
+}
+
+/// Writes a picture of the cells in `bounds` to `path` as a PPM image, one pixel per cell.
+///
+/// Each location's area gets its own color, which is darker if the area is infinite. Cells that
+/// are tied are dark gray, and the locations themselves are white. Cells whose total Manhattan
+/// distance to every location is less than `threshold` are washed out halfway to white.
+pub fn write_region_map(
+    path: &str,
+    locations: &[Location],
+    metric: voronoi::Metric,
+    bounds: Bounds,
+    threshold: u32,
+) -> io::Result<()> {
+    let areas = voronoi::areas(locations, metric);
+    let safe = safe_region(locations, threshold).cells;
+
+    let image = bounds.map(|x, y| {
+        let color = match voronoi::closest(locations, metric, x, y) {
+            Some(i) if locations[i] == (Location { x, y }) => [255, 255, 255],
+            Some(i) => {
+                // Stepping around the color wheel by the golden ratio keeps neighboring indexes' colors far apart.
+                let hue = (i as f64 * 0.618_034).fract();
+                let value = match areas[i] {
+                    voronoi::Area::Finite(_) => 0.9,
+                    voronoi::Area::Infinite => 0.45,
+                };
+
+                hsv_to_rgb(hue, 0.7, value)
+            }
+            None => [48, 48, 48],
+        };
+
+        if safe.contains(&(x, y)) {
+            [color[0] / 2 + 128, color[1] / 2 + 128, color[2] / 2 + 128]
+        } else {
+            color
+        }
+    });
+
+    util::write_ppm(path, &image)
 }
 
 /// On the other hand, if the coordinates are safe, maybe the best you can do
@@ -143,36 +547,22 @@ pub fn six_a() -> u32 {
 /// What is the size of the region containing all locations which have
 /// a total distance to all given coordinates of less than 10000?
 pub fn six_b() -> usize {
-    let locations = load_locations();
-
-    let mut location_grid = initialize_grid(&locations);
-
-    // Mark each spot on the grid with the total distance to all Locations.
-    for x in location_grid.min_x..location_grid.max_x {
-        for y in location_grid.min_y..location_grid.max_y {
-            location_grid.grid[x as usize][y as usize] = locations
-                .iter()
-                .map(|location| manhattan_distance(location.x, location.y, x, y) as i32)
-                .sum();
-        }
-    }
-
-    location_grid
-        .grid
-        .iter()
-        .flatten()
-        .filter(|&&total_distance| total_distance != SENTINEL_LOCATION_ID && total_distance < 10_000)
-        .count()
+    let locations = load_locations("src/inputs/6.txt");
+    safe_region(&locations, 10_000).cells.len()
 }
 
 #[cfg(test)]
 mod test {
+    use super::voronoi::{Area, Metric};
     use super::*;
 
     #[test]
     fn test_solutions() {
         assert_eq!(six_a(), 4284);
         assert_eq!(six_b(), 35490);
+
+        let locations = load_locations("src/inputs/6.txt");
+        assert_eq!(safe_region_cell_by_cell(&locations, 10_000).cells.len(), 35490);
     }
 
     #[test]
@@ -181,4 +571,181 @@ mod test {
         assert_eq!(manhattan_distance(2, 4, 0, 6), 4);
     }
 
+    #[test]
+    fn test_sample_areas() {
+        let locations = load_locations("src/inputs/6_sample.txt");
+
+        // "In this example, the areas of coordinates A, B, C, and F are infinite... the largest
+        // area is 17, for location E." D's area is 9.
+        assert_eq!(
+            voronoi::areas(&locations, Metric::Manhattan),
+            vec![
+                Area::Infinite,
+                Area::Infinite,
+                Area::Infinite,
+                Area::Finite(9),
+                Area::Finite(17),
+                Area::Infinite
+            ]
+        );
+
+        // Moving everything somewhere else, negative coordinates included, changes nothing.
+        let moved: Vec<Location> = locations
+            .iter()
+            .map(|location| Location {
+                x: location.x - 20,
+                y: location.y - 7,
+            })
+            .collect();
+
+        for &metric in &[Metric::Manhattan, Metric::Chebyshev, Metric::Euclidean] {
+            assert_eq!(voronoi::areas(&moved, metric), voronoi::areas(&locations, metric));
+        }
+    }
+
+    /// Counts the cells closest to each location in a big square around the origin the slow way.
+    fn brute_force_areas(locations: &[Location], metric: Metric, radius: i32) -> Vec<usize> {
+        let mut areas = vec![0; locations.len()];
+
+        for (x, y) in iproduct!(-radius..=radius, -radius..=radius) {
+            if let Some(closest) = voronoi::closest(locations, metric, x, y) {
+                areas[closest] += 1;
+            }
+        }
+
+        areas
+    }
+
+    #[test]
+    fn test_areas_match_brute_force() {
+        let mut locations = load_locations("src/inputs/6_sample.txt");
+        locations.extend(&[
+            Location { x: 4, y: 2 },
+            Location { x: 6, y: 7 },
+            Location { x: -3, y: 5 },
+            Location { x: 3, y: 6 },
+        ]);
+
+        for &metric in &[Metric::Manhattan, Metric::Chebyshev, Metric::Euclidean] {
+            let areas = voronoi::areas(&locations, metric);
+            let small = brute_force_areas(&locations, metric, 60);
+            let big = brute_force_areas(&locations, metric, 120);
+
+            for i in 0..locations.len() {
+                match areas[i] {
+                    Area::Finite(area) => assert_eq!((area, area), (small[i], big[i]), "{:?} {}", metric, i),
+                    Area::Infinite => assert!(small[i] < big[i], "{:?} {}", metric, i),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_euclidean_hull_edge() {
+        // (2, 0) is in the middle of the bottom edge of the others' hull, so it's closest to every
+        // cell straight below it, no matter how far down.
+        let locations = vec![
+            Location { x: 0, y: 0 },
+            Location { x: 2, y: 0 },
+            Location { x: 4, y: 0 },
+            Location { x: 2, y: 5 },
+        ];
+        assert_eq!(voronoi::areas(&locations, Metric::Euclidean)[1], Area::Infinite);
+
+        // Two locations in the same place are always tied.
+        let locations = vec![Location { x: 0, y: 0 }, Location { x: 0, y: 0 }];
+        assert_eq!(
+            voronoi::areas(&locations, Metric::Euclidean),
+            vec![Area::Finite(0), Area::Finite(0)]
+        );
+    }
+
+    #[test]
+    fn test_safe_region() {
+        let locations = load_locations("src/inputs/6_sample.txt");
+
+        // "In this example, the region ... would have a size of 16."
+        let region = safe_region(&locations, 32);
+        assert_eq!(region.cells.len(), 16);
+        assert!(region.connected);
+        assert!(region.cells.contains(&(4, 3)));
+        assert!(!region.cells.contains(&(4, 2)));
+
+        // With a big enough threshold, the region spills past the locations' bounds, which go from
+        // (1, 1) to (8, 9).
+        let region = safe_region(&locations, 100);
+        assert!(region.cells.contains(&(-3, 5)));
+        assert!(region.cells.contains(&(4, 13)));
+        let slow = iproduct!(-50..50, -50..50)
+            .filter(|&(x, y)| total_distance(&locations, x, y) < 100)
+            .count();
+        assert_eq!(region.cells.len(), slow);
+        assert_eq!(region.cells, safe_region_cell_by_cell(&locations, 100).cells);
+
+        assert!(safe_region(&locations, 0).cells.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "a safe region needs at least one location")]
+    fn test_safe_region_without_locations() {
+        safe_region(&[], 32);
+    }
+
+    #[test]
+    fn test_render() {
+        let locations = load_locations("src/inputs/6_sample.txt");
+        let bounds = Bounds {
+            min_x: 0,
+            max_x: 9,
+            min_y: 0,
+            max_y: 9,
+        };
+
+        assert_eq!(
+            voronoi::render(&locations, Metric::Manhattan, bounds),
+            [
+                "aaaaa.cccc",
+                "aAaaa.cccc",
+                "aaaddecccc",
+                "aadddeccCc",
+                "..dDdeeccc",
+                "bb.deEeecc",
+                "bBb.eeee..",
+                "bbb.eeefff",
+                "bbb.eeffff",
+                "bbb.ffffFf",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_write_region_map() {
+        let locations = load_locations("src/inputs/6_sample.txt");
+        let bounds = Bounds {
+            min_x: 0,
+            max_x: 9,
+            min_y: 0,
+            max_y: 9,
+        };
+
+        let path = std::env::temp_dir().join("advent_2018_test_region_map.ppm");
+        let path = path.to_str().unwrap();
+        write_region_map(path, &locations, Metric::Manhattan, bounds, 32).unwrap();
+
+        let image = fs::read(path).unwrap();
+        let header = b"P6\n10 10\n255\n";
+        assert!(image.starts_with(header));
+        let pixel = |x: usize, y: usize| &image[header.len() + 3 * (10 * y + x)..header.len() + 3 * (10 * y + x) + 3];
+
+        // A at (1, 1) is a location; (5, 0) is tied; (0, 0) is in A's infinite area.
+        assert_eq!(pixel(1, 1), [255, 255, 255]);
+        assert_eq!(pixel(5, 0), [48, 48, 48]);
+        assert_eq!(pixel(0, 0), hsv_to_rgb(0.0, 0.7, 0.45));
+
+        // (4, 3) is in D's finite area, and in the safe region; (3, 2) is only in D's area.
+        let d = hsv_to_rgb((3.0 * 0.618_034f64).fract(), 0.7, 0.9);
+        assert_eq!(pixel(3, 2), d);
+        assert_eq!(pixel(4, 3), [d[0] / 2 + 128, d[1] / 2 + 128, d[2] / 2 + 128]);
+    }
 }
@@ -1,9 +1,13 @@
 use std::fs;
+use std::io;
+use std::ops::Range;
 
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use crate::util::{self, Rgb};
+
 #[derive(Debug, PartialEq)]
 struct Point {
     x: i32,
@@ -32,128 +36,390 @@ impl Point {
     }
 }
 
-struct Grid {
+/// Reads the capital letters that AoC puzzles like day 10 spell out in lit-up pixels.
+pub mod ocr {
+    use hashbrown::HashMap;
+    use lazy_static::lazy_static;
+
+    /// The letters in the 6-wide, 10-tall font used in 2018, drawn side by side.
+    /// Only the letters that have actually shown up in puzzle answers are known.
+    const LARGE_FONT_LETTERS: &str = "ABCEFGHJKLNPRXZ";
+    const LARGE_FONT: &str = "
+..##....#####....####...######..######...####...#....#.....###..#....#..#.......#....#..#####...#####...#....#..######..
+.#..#...#....#..#....#..#.......#.......#....#..#....#......#...#...#...#.......##...#..#....#..#....#..#....#.......#..
+#....#..#....#..#.......#.......#.......#.......#....#......#...#..#....#.......##...#..#....#..#....#...#..#........#..
+#....#..#....#..#.......#.......#.......#.......#....#......#...#.#.....#.......#.#..#..#....#..#....#...#..#.......#...
+#....#..#####...#.......#####...#####...#.......######......#...##......#.......#.#..#..#####...#####.....##.......#....
+######..#....#..#.......#.......#.......#..###..#....#......#...##......#.......#..#.#..#.......#..#......##......#.....
+#....#..#....#..#.......#.......#.......#....#..#....#......#...#.#.....#.......#..#.#..#.......#...#....#..#....#......
+#....#..#....#..#.......#.......#.......#....#..#....#..#...#...#..#....#.......#...##..#.......#...#....#..#...#.......
+#....#..#....#..#....#..#.......#.......#...##..#....#..#...#...#...#...#.......#...##..#.......#....#..#....#..#.......
+#....#..#####....####...######..#........###.#..#....#...###....#....#..######..#....#..#.......#....#..#....#..######..
+";
+
+    /// The letters in the 4-wide, 6-tall font used in later years, drawn side by side.
+    pub(super) const SMALL_FONT_LETTERS: &str = "ABCEFGHIJKLOPRSUYZ";
+    pub(super) const SMALL_FONT: &str = "
+.##..###...##..####.####..##..#..#..###...##.#..#.#.....##..###..###...###.#..#.#...#.####.
+#..#.#..#.#..#.#....#....#..#.#..#...#.....#.#.#..#....#..#.#..#.#..#.#....#..#.#...#....#.
+#..#.###..#....###..###..#....####...#.....#.##...#....#..#.#..#.#..#.#....#..#..#.#....#..
+####.#..#.#....#....#....#.##.#..#...#.....#.#.#..#....#..#.###..###...##..#..#...#....#...
+#..#.#..#.#..#.#....#....#..#.#..#...#..#..#.#.#..#....#..#.#....#.#.....#.#..#...#...#....
+#..#.###...##..####.#.....###.#..#..###..##..#..#.####..##..#....#..#.###...##....#...####.
+";
+
+    lazy_static! {
+        /// Maps each letter in both fonts, as columns of pixels, to the letter.
+        static ref GLYPHS: HashMap<Vec<Vec<bool>>, char> = {
+            let large = split_letters(&to_grid(LARGE_FONT)).into_iter().zip(LARGE_FONT_LETTERS.chars());
+            let small = split_letters(&to_grid(SMALL_FONT)).into_iter().zip(SMALL_FONT_LETTERS.chars());
+
+            large.chain(small).collect()
+        };
+    }
+
+    /// Turns rows of `#`s and `.`s into a grid of pixels, indexed as grid[x][y].
+    pub(super) fn to_grid(drawing: &str) -> Vec<Vec<bool>> {
+        let rows = drawing.trim().lines().map(|row| row.as_bytes()).collect::<Vec<&[u8]>>();
+
+        (0..rows[0].len())
+            .map(|x| rows.iter().map(|row| row[x] == b'#').collect())
+            .collect()
+    }
+
+    /// Splits `grid` into its letters, which are separated by blank columns.
+    /// Each letter's pixels run from the top of `grid` to the bottom.
+    fn split_letters(grid: &[Vec<bool>]) -> Vec<Vec<Vec<bool>>> {
+        let mut letters = vec![];
+        let mut letter: Vec<Vec<bool>> = vec![];
+
+        for column in grid {
+            if column.iter().any(|&pixel| pixel) {
+                letter.push(column.clone());
+            } else if !letter.is_empty() {
+                letters.push(letter);
+                letter = vec![];
+            }
+        }
+
+        if !letter.is_empty() {
+            letters.push(letter);
+        }
+
+        letters
+    }
+
+    /// Returns the text spelled out by `grid`, which is indexed as grid[x][y].
+    ///
+    /// Letters are told apart by the blank columns between them, and any letter that isn't
+    /// in either font comes out as a `?`.
+    pub fn read(grid: &[Vec<bool>]) -> String {
+        let lit_rows = (0..grid.first().map_or(0, |column| column.len()))
+            .filter(|&y| grid.iter().any(|column| column[y]))
+            .collect::<Vec<usize>>();
+
+        let (top, bottom) = match (lit_rows.first(), lit_rows.last()) {
+            (Some(&top), Some(&bottom)) => (top, bottom),
+            _ => return String::new(),
+        };
+
+        let trimmed = grid
+            .iter()
+            .map(|column| column[top..=bottom].to_vec())
+            .collect::<Vec<Vec<bool>>>();
+
+        split_letters(&trimmed)
+            .iter()
+            .map(|letter| *GLYPHS.get(letter).unwrap_or(&'?'))
+            .collect()
+    }
+}
+
+/// The lights in the sky, which all move in straight lines at constant speeds.
+struct Sky {
+    /// Each point's position and velocity at time 0.
     points: Vec<Point>,
-    min_x: i32,
-    min_y: i32,
-    max_x: i32,
-    max_y: i32,
 }
 
-impl Grid {
-    fn bounds(points: &[Point]) -> (i32, i32, i32, i32) {
-        let (min_x, max_x) = points.iter().map(|point| point.x).minmax().into_option().unwrap();
-        let (min_y, max_y) = points.iter().map(|point| point.y).minmax().into_option().unwrap();
+impl Sky {
+    fn new(points: Vec<Point>) -> Self {
+        Sky { points }
+    }
+
+    /// Returns each point's (x, y) position after `seconds` seconds.
+    fn positions_at(&self, seconds: i64) -> Vec<(i64, i64)> {
+        self.points
+            .iter()
+            .map(|point| {
+                (
+                    i64::from(point.x) + i64::from(point.dx) * seconds,
+                    i64::from(point.y) + i64::from(point.dy) * seconds,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns (min_x, max_x, min_y, max_y).
+    fn bounds(positions: &[(i64, i64)]) -> (i64, i64, i64, i64) {
+        let (min_x, max_x) = positions.iter().map(|&(x, _)| x).minmax().into_option().unwrap();
+        let (min_y, max_y) = positions.iter().map(|&(_, y)| y).minmax().into_option().unwrap();
 
         (min_x, max_x, min_y, max_y)
     }
 
-    fn new(points: Vec<Point>) -> Self {
-        let (min_x, max_x, min_y, max_y) = Grid::bounds(&points);
-
-        Grid {
-            min_x,
-            max_x,
-            min_y,
-            max_y,
-            points,
-        }
+    /// Returns the width and height of the points' bounding box after `seconds` seconds.
+    fn size_at(&self, seconds: i64) -> (i64, i64) {
+        let (min_x, max_x, min_y, max_y) = Sky::bounds(&self.positions_at(seconds));
+
+        (max_x - min_x + 1, max_y - min_y + 1)
     }
 
-    fn advance(&mut self) {
-        for point in &mut self.points {
-            point.x += point.dx;
-            point.y += point.dy;
-        }
+    /// Returns the number of cells in the points' bounding box after `seconds` seconds.
+    fn area_at(&self, seconds: i64) -> i64 {
+        let (width, height) = self.size_at(seconds);
+
+        width * height
+    }
+
+    /// Returns the width plus the height of the points' bounding box after `seconds` seconds.
+    fn half_perimeter_at(&self, seconds: i64) -> i64 {
+        let (width, height) = self.size_at(seconds);
 
-        let (min_x, max_x, min_y, max_y) = Grid::bounds(&self.points);
-        self.min_x = min_x;
-        self.max_x = max_x;
-        self.min_y = min_y;
-        self.max_y = max_y;
+        width + height
     }
 
-    fn to_vec(&self) -> Vec<Vec<bool>> {
-        let mut grid =
-            vec![vec![false; (self.max_y - self.min_y) as usize + 1]; (self.max_x - self.min_x) as usize + 1];
+    /// Returns the points' bounding box after `seconds` seconds as a grid, indexed as grid[x][y],
+    /// where a cell is true if at least one point is there.
+    fn frame_at(&self, seconds: i64) -> Vec<Vec<bool>> {
+        let positions = self.positions_at(seconds);
+        let (min_x, max_x, min_y, max_y) = Sky::bounds(&positions);
+
+        let mut grid = vec![vec![false; (max_y - min_y) as usize + 1]; (max_x - min_x) as usize + 1];
 
-        for point in &self.points {
-            grid[(point.x - self.min_x) as usize][(point.y - self.min_y) as usize] = true;
+        for (x, y) in positions {
+            grid[(x - min_x) as usize][(y - min_y) as usize] = true;
         }
 
         grid
     }
 
-    fn print(&self) {
-        let grid = self.to_vec();
+    /// Returns the points' positions after `seconds` seconds, squeezed into a `width`x`height` viewport
+    /// whose top-left corner shows (min_x, min_y) and which shows everything down to (max_x, max_y).
+    /// Each pixel of the viewport covers the same whole number of cells in both directions, so the
+    /// picture keeps its proportions; it's never zoomed in past one cell per pixel.
+    fn viewport_frame_at(
+        &self,
+        seconds: i64,
+        bounds: (i64, i64, i64, i64),
+        width: usize,
+        height: usize,
+    ) -> Vec<Vec<bool>> {
+        let (min_x, max_x, min_y, max_y) = bounds;
+        let cells_per_pixel = ((max_x - min_x) / width as i64 + 1).max((max_y - min_y) / height as i64 + 1);
+
+        let mut grid = vec![vec![false; height]; width];
+
+        for (x, y) in self.positions_at(seconds) {
+            if x < min_x || y < min_y {
+                continue;
+            }
 
-        for y in 0..grid[0].len() {
-            let mut row = String::new();
+            let (pixel_x, pixel_y) = (
+                ((x - min_x) / cells_per_pixel) as usize,
+                ((y - min_y) / cells_per_pixel) as usize,
+            );
 
-            for column in grid.iter() {
-                row.push(if column[y] { 'X' } else { '.' });
+            if pixel_x < width && pixel_y < height {
+                grid[pixel_x][pixel_y] = true;
             }
-
-            println!("{}", row);
         }
+
+        grid
     }
-}
 
-const LONG_LINE_THRESHOLD: u32 = 5;
+    /// Returns the time at which the points' bounding box is smallest, which is when they spell out the message.
+    ///
+    /// Each point moves in a straight line, so the width and height of the bounding box are both
+    /// convex functions of time, and so is their sum. That means we can binary search for the moment
+    /// at which the width plus the height stops shrinking, instead of stepping through every second
+    /// one at a time. The area is a product of convex functions, though, which can have more than one
+    /// local minimum, so from there this follows the area downhill to the nearest time at which it's
+    /// smallest. When the points converge on the message in both directions at once, as they do in
+    /// the puzzle, that's the same moment.
+    fn message_time(&self) -> i64 {
+        // Find a time at which the width plus the height has stopped shrinking, doubling as we go.
+        let mut high = 1;
+        while self.half_perimeter_at(high) > self.half_perimeter_at(high + 1) {
+            high *= 2;
+        }
+
+        let mut low = 0;
+        while low < high {
+            let mid = (low + high) / 2;
 
-fn num_lines(grid: Vec<Vec<bool>>) -> usize {
-    fn longest_contiguous_line(line: &[bool]) -> bool {
-        line.iter().fold(0, |acc, value| {
-            if acc >= LONG_LINE_THRESHOLD || *value {
-                acc + 1
+            if self.half_perimeter_at(mid) <= self.half_perimeter_at(mid + 1) {
+                high = mid;
             } else {
-                0
+                low = mid + 1;
             }
-        }) >= LONG_LINE_THRESHOLD
-    }
+        }
 
-    let mut all_lines = vec![];
+        // Earlier seconds win ties, so only look later if the area doesn't get any smaller earlier.
+        let mut time = low;
+        while time > 0 && self.area_at(time - 1) <= self.area_at(time) {
+            time -= 1;
+        }
+        while self.area_at(time + 1) < self.area_at(time) {
+            time += 1;
+        }
 
-    all_lines.extend(grid.iter().cloned());
+        time
+    }
+}
 
-    for y in 0..grid[0].len() {
-        let mut row = vec![];
+/// The message that the lights in the sky spell out, and when they spell it.
+pub struct Message {
+    /// How many seconds it takes for the message to appear.
+    pub seconds: u32,
+    /// The lights' bounding box at that moment, indexed as frame[x][y].
+    pub frame: Vec<Vec<bool>>,
+}
 
-        for column in grid.iter() {
-            row.push(column[y]);
-        }
+impl Message {
+    /// Returns the frame's rows, top to bottom, with an `X` for each lit cell and a `.` for each dark one.
+    pub fn rows(&self) -> Vec<String> {
+        (0..self.frame[0].len())
+            .map(|y| {
+                self.frame
+                    .iter()
+                    .map(|column| if column[y] { 'X' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
 
-        all_lines.push(row);
+    /// Returns the text that the frame spells out.
+    pub fn text(&self) -> String {
+        ocr::read(&self.frame)
     }
+}
 
-    all_lines.iter().filter(|line| longest_contiguous_line(line)).count()
+/// How an animation of the sky decides which part of the sky to show.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zoom {
+    /// Every frame shows the same part of the sky: the smallest box that holds every point
+    /// in every frame. The points visibly rush together and fly apart again.
+    Fixed,
+    /// Each frame zooms in on wherever the points are at that moment.
+    Follow,
 }
 
-const TOO_LARGE_WIDTH: i32 = 100;
-const TOO_LARGE_HEIGHT: i32 = 100;
+/// A series of frames of the sky, one per second.
+pub struct Animation {
+    /// Each frame's time and pixels, indexed as pixels[x][y].
+    pub frames: Vec<(i64, Vec<Vec<bool>>)>,
+}
+
+impl Animation {
+    /// Returns every frame as rows of `X`s and `.`s, each preceded by a line giving its time,
+    /// for flipping through in a text editor or with `less`.
+    pub fn flipbook(&self) -> String {
+        self.frames
+            .iter()
+            .map(|(seconds, pixels)| {
+                let rows = (0..pixels[0].len())
+                    .map(|y| pixels.iter().map(|column| if column[y] { 'X' } else { '.' }).collect())
+                    .collect::<Vec<String>>();
+
+                format!("t = {}\n{}\n", seconds, rows.join("\n"))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn write_flipbook(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.flipbook())
+    }
 
-pub fn ten() -> u32 {
-    let contents = fs::read_to_string("src/inputs/10.txt").unwrap();
-    let points: Vec<Point> = contents.lines().map(Point::new).collect();
+    /// Writes each frame to its own PPM image, named like `{path_prefix}00042.ppm` after the frame's time,
+    /// with lit cells in white on black. Returns the paths of the images, in order.
+    pub fn write_ppm_frames(&self, path_prefix: &str) -> io::Result<Vec<String>> {
+        let mut paths = vec![];
+
+        for (seconds, pixels) in &self.frames {
+            let path = format!("{}{:05}.ppm", path_prefix, seconds);
+            let image = pixels
+                .iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .map(|&lit| if lit { [255, 255, 255] } else { [0, 0, 0] })
+                        .collect()
+                })
+                .collect::<Vec<Vec<Rgb>>>();
+
+            util::write_ppm(&path, &image)?;
+            paths.push(path);
+        }
 
-    let mut grid = Grid::new(points);
+        Ok(paths)
+    }
+}
 
-    let mut seconds = 0;
+/// Returns an animation of the sky in the file at `path`, with one `width`x`height` frame per second in `seconds`.
+pub fn animate(path: &str, seconds: Range<i64>, width: usize, height: usize, zoom: Zoom) -> Animation {
+    let contents = fs::read_to_string(path).unwrap();
+    let sky = Sky::new(contents.lines().map(Point::new).collect());
+
+    // The points move in straight lines, so the furthest any of them gets in any direction
+    // over the course of the animation is at its very beginning or its very end.
+    let (first_min_x, first_max_x, first_min_y, first_max_y) = Sky::bounds(&sky.positions_at(seconds.start));
+    let (last_min_x, last_max_x, last_min_y, last_max_y) = Sky::bounds(&sky.positions_at(seconds.end - 1));
+    let fixed_bounds = (
+        first_min_x.min(last_min_x),
+        first_max_x.max(last_max_x),
+        first_min_y.min(last_min_y),
+        first_max_y.max(last_max_y),
+    );
+
+    Animation {
+        frames: seconds
+            .map(|second| {
+                let bounds = match zoom {
+                    Zoom::Fixed => fixed_bounds,
+                    Zoom::Follow => Sky::bounds(&sky.positions_at(second)),
+                };
+
+                (second, sky.viewport_frame_at(second, bounds, width, height))
+            })
+            .collect(),
+    }
+}
 
-    loop {
-        grid.advance();
-        seconds += 1;
+fn find_message(path: &str) -> Message {
+    let contents = fs::read_to_string(path).unwrap();
+    let sky = Sky::new(contents.lines().map(Point::new).collect());
+    let seconds = sky.message_time();
 
-        if (grid.max_x - grid.min_x) > TOO_LARGE_WIDTH || (grid.max_y - grid.min_y) > TOO_LARGE_HEIGHT {
-            continue;
-        } else if num_lines(grid.to_vec()) > 8 {
-            break;
-        }
+    Message {
+        seconds: seconds as u32,
+        frame: sky.frame_at(seconds),
     }
+}
 
-    grid.print();
+/// Returns the message in your puzzle input, along with how long it takes to appear.
+pub fn ten() -> Message {
+    find_message("src/inputs/10.txt")
+}
+
+/// What message will eventually appear in the sky?
+pub fn ten_a() -> String {
+    ten().text()
+}
 
-    seconds
+/// Exactly how many seconds would they have needed to wait for that message to appear?
+pub fn ten_b() -> u32 {
+    ten().seconds
 }
 
 #[cfg(test)]
@@ -162,7 +428,8 @@ mod test {
 
     #[test]
     fn test_solution() {
-        assert_eq!(ten(), 10355);
+        assert_eq!(ten_a(), "HRPHBRKG");
+        assert_eq!(ten_b(), 10355);
     }
 
     #[test]
@@ -187,4 +454,96 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn test_sample() {
+        let message = find_message("src/inputs/10_sample.txt");
+
+        assert_eq!(message.seconds, 3);
+        assert_eq!(
+            message.rows(),
+            vec![
+                "X...X..XXX",
+                "X...X...X.",
+                "X...X...X.",
+                "XXXXX...X.",
+                "X...X...X.",
+                "X...X...X.",
+                "X...X...X.",
+                "X...X..XXX",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_time() {
+        // The box is 1 + |t - 10| wide and 1 + |2t - 80| tall, so its area has a local minimum of 61
+        // at 10 seconds, but it's smallest at 40 seconds, when it's 31x1.
+        let sky = Sky::new(vec![
+            Point {
+                x: -10,
+                y: -80,
+                dx: 1,
+                dy: 2,
+            },
+            Point {
+                x: 0,
+                y: 0,
+                dx: 0,
+                dy: 0,
+            },
+        ]);
+
+        assert_eq!(sky.area_at(10), 61);
+        assert_eq!(sky.message_time(), 40);
+        assert_eq!(sky.area_at(40), 31);
+    }
+
+    #[test]
+    fn test_ocr() {
+        assert_eq!(ocr::read(&ocr::to_grid(ocr::SMALL_FONT)), ocr::SMALL_FONT_LETTERS);
+        assert_eq!(
+            ocr::read(&ocr::to_grid(
+                "
+........................
+#..#.####.#....#.....##.
+#..#.#....#....#....#..#
+####.###..#....#....#..#
+#..#.#....#....#....#..#
+#..#.#....#....#....#..#
+#..#.####.####.####..##.
+........................
+"
+            )),
+            "HELLO"
+        );
+
+        // The sample's font isn't one we know.
+        assert_eq!(find_message("src/inputs/10_sample.txt").text(), "??");
+        assert_eq!(ocr::read(&vec![vec![false; 3]; 3]), "");
+    }
+
+    #[test]
+    fn test_animate() {
+        let animation = animate("src/inputs/10_sample.txt", 0..5, 10, 8, Zoom::Follow);
+        let message = find_message("src/inputs/10_sample.txt");
+
+        assert_eq!(animation.frames.len(), 5);
+        assert_eq!(animation.frames[3], (3, message.frame.clone()));
+        assert!(animation.flipbook().starts_with("t = 0\n"));
+        assert!(animation
+            .flipbook()
+            .contains(&format!("t = 3\n{}\n", message.rows().join("\n"))));
+
+        // With a fixed zoom, the points start out spread across the whole viewport and then bunch up.
+        let animation = animate("src/inputs/10_sample.txt", 0..4, 10, 8, Zoom::Fixed);
+        let lit_columns = |pixels: &Vec<Vec<bool>>| pixels.iter().filter(|column| column.contains(&true)).count();
+        assert!(lit_columns(&animation.frames[0].1) > lit_columns(&animation.frames[3].1));
+
+        let path_prefix = std::env::temp_dir().join("advent_2018_test_animate_");
+        let paths = animation.write_ppm_frames(path_prefix.to_str().unwrap()).unwrap();
+        assert_eq!(paths.len(), 4);
+        assert!(paths[3].ends_with("advent_2018_test_animate_00003.ppm"));
+        assert!(fs::read(&paths[3]).unwrap().starts_with(b"P6\n10 8\n255\n"));
+    }
 }
@@ -3,6 +3,8 @@ use std::fs;
 use serde::Deserialize;
 use serde_scan::scan;
 
+use crate::util::SummedAreaTable;
+
 #[derive(Deserialize, Debug, PartialEq)]
 struct Claim {
     id: i32,
@@ -35,40 +37,51 @@ fn mark_claim_on_grid(grid: &mut FabricGrid, claim: &Claim) {
     }
 }
 
+/// Returns how many claims each square inch of fabric is within.
+fn claim_counts(claims: &[Claim]) -> FabricGrid {
+    let mut grid: FabricGrid = vec![vec![0; 1000]; 1000];
+
+    for claim in claims {
+        mark_claim_on_grid(&mut grid, claim);
+    }
+
+    grid
+}
+
+/// Returns a summed-area table over a grid whose cells are 1 if they're within two or more claims and 0 otherwise.
+fn contested_squares(grid: &FabricGrid) -> SummedAreaTable<i32> {
+    let contested: FabricGrid = grid
+        .iter()
+        .map(|column| column.iter().map(|&count| if count > 1 { 1 } else { 0 }).collect())
+        .collect();
+
+    SummedAreaTable::new(&contested)
+}
+
 // How many square inches of fabric are within two or more claims?
 pub fn three_a() -> usize {
     let contents = fs::read_to_string("src/inputs/3.txt").unwrap();
     let claims: Vec<Claim> = contents.lines().map(Claim::new).collect();
-    let mut grid: FabricGrid = vec![vec![0; 1000]; 1000];
+    let grid = claim_counts(&claims);
 
-    for claim in &claims {
-        mark_claim_on_grid(&mut grid, &claim);
-    }
-
-    grid.iter().map(|x| x.iter()).flatten().filter(|x| **x > 1).count()
+    grid.iter().flatten().filter(|&&count| count > 1).count()
 }
 
 // What is the ID of the only claim that doesn't overlap?
 pub fn three_b() -> i32 {
     let contents = fs::read_to_string("src/inputs/3.txt").unwrap();
     let claims: Vec<Claim> = contents.lines().map(Claim::new).collect();
-    let mut grid: FabricGrid = vec![vec![0; 1000]; 1000];
+    let table = contested_squares(&claim_counts(&claims));
 
     for claim in &claims {
-        mark_claim_on_grid(&mut grid, &claim);
-    }
-
-    for claim in &claims {
-        let mut contested = false;
-        for i in claim.x..(claim.x + claim.width) {
-            for j in claim.y..(claim.y + claim.height) {
-                if grid[i as usize][j as usize] > 1 {
-                    contested = true;
-                }
-            }
-        }
+        let num_contested_squares = table.rectangle_sum(
+            claim.x as usize,
+            claim.y as usize,
+            claim.width as usize,
+            claim.height as usize,
+        );
 
-        if !contested {
+        if num_contested_squares == 0 {
             return claim.id;
         }
     }
@@ -100,5 +113,4 @@ mod test {
         assert_eq!(three_a(), 101196);
         assert_eq!(three_b(), 243);
     }
-
 }
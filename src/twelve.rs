@@ -3,19 +3,395 @@ use std::fs;
 use lazy_static::lazy_static;
 use regex::Regex;
 
+/// A general-purpose engine for one-dimensional cellular automata, which day 12 turns out to be.
+/// See https://en.wikipedia.org/wiki/Elementary_cellular_automaton
+pub mod automaton {
+    use std::io;
+
+    use crate::util::{self, Rgb};
+
+    /// What lies past either end of an automaton's row of cells.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Boundary {
+        /// The row is a ring: the leftmost cell's left neighbor is the rightmost cell, and vice versa.
+        Wrap,
+        /// The row stretches out forever in both directions, and every cell we haven't seen yet is dead.
+        Infinite,
+    }
+
+    /// A lookup table that maps each possible neighborhood of `2 * radius + 1` cells to the state
+    /// of the neighborhood's center cell in the next generation.
+    ///
+    /// Neighborhoods are indexed by reading them as binary numbers, leftmost cell first,
+    /// which is the same convention that Wolfram codes use.
+    /// Neighborhoods that were never given a result produce a dead cell.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Rule {
+        radius: usize,
+        table: Vec<bool>,
+    }
+
+    impl Rule {
+        /// Returns a rule in which every neighborhood produces a dead cell.
+        pub fn new(radius: usize) -> Self {
+            Rule {
+                radius,
+                table: vec![false; 1 << (2 * radius + 1)],
+            }
+        }
+
+        /// Returns the elementary (radius 1) rule with the given Wolfram rule number, e.g. Rule 30.
+        pub fn elementary(number: u8) -> Self {
+            Rule::wolfram(1, u64::from(number))
+        }
+
+        /// Returns the rule whose result for the neighborhood with index `i` is bit `i` of `code`.
+        /// Only radii 0, 1 and 2 have few enough neighborhoods for their codes to fit in a u64.
+        pub fn wolfram(radius: usize, code: u64) -> Self {
+            assert!(radius <= 2, "Wolfram codes for radius {} don't fit in a u64", radius);
+
+            let mut rule = Rule::new(radius);
+
+            for (i, result) in rule.table.iter_mut().enumerate() {
+                *result = (code >> i) & 1 == 1;
+            }
+
+            rule
+        }
+
+        /// Returns the totalistic rule whose result for a neighborhood containing `n` live cells
+        /// is bit `n` of `code`.
+        pub fn totalistic(radius: usize, code: u64) -> Self {
+            let mut rule = Rule::new(radius);
+
+            for (i, result) in rule.table.iter_mut().enumerate() {
+                *result = (code >> i.count_ones()) & 1 == 1;
+            }
+
+            rule
+        }
+
+        /// Records that `pattern` produces `result`.
+        pub fn set(&mut self, pattern: &[bool], result: bool) {
+            assert_eq!(
+                pattern.len(),
+                self.width(),
+                "pattern doesn't match a rule of radius {}",
+                self.radius
+            );
+
+            let index = pattern.iter().fold(0, |acc, &cell| (acc << 1) | cell as usize);
+            self.table[index] = result;
+        }
+
+        pub fn radius(&self) -> usize {
+            self.radius
+        }
+
+        fn width(&self) -> usize {
+            2 * self.radius + 1
+        }
+    }
+
+    /// A row of live and dead cells that evolves one generation at a time according to a `Rule`.
+    pub struct Automaton {
+        rule: Rule,
+        boundary: Boundary,
+        cells: Vec<bool>,
+        /// The position of `cells[0]`. This only ever changes under `Boundary::Infinite`,
+        /// where `cells` is trimmed to run from the leftmost live cell to the rightmost one.
+        origin: i64,
+        generation: u64,
+    }
+
+    impl Automaton {
+        /// Returns an automaton whose cell at position 0 is `initial_state[0]`.
+        pub fn new(initial_state: Vec<bool>, rule: Rule, boundary: Boundary) -> Self {
+            // If an all-dead neighborhood came to life, an infinite row would fill up with
+            // infinitely many live cells on the very first generation.
+            assert!(
+                boundary == Boundary::Wrap || !rule.table[0],
+                "rules that bring empty space to life can't run on an infinite row"
+            );
+            assert!(
+                boundary == Boundary::Infinite || !initial_state.is_empty(),
+                "a row that wraps around needs at least one cell"
+            );
+
+            let mut automaton = Automaton {
+                rule,
+                boundary,
+                cells: initial_state,
+                origin: 0,
+                generation: 0,
+            };
+
+            if boundary == Boundary::Infinite {
+                automaton.trim();
+            }
+
+            automaton
+        }
+
+        /// Advances the automaton by one generation.
+        pub fn step(&mut self) {
+            self.cells = match self.boundary {
+                Boundary::Wrap => self.next_wrapped(),
+                Boundary::Infinite => self.next_infinite(),
+            };
+
+            if self.boundary == Boundary::Infinite {
+                self.trim();
+            }
+
+            self.generation += 1;
+        }
+
+        fn next_wrapped(&self) -> Vec<bool> {
+            let num_cells = self.cells.len() as i64;
+            let radius = self.rule.radius as i64;
+            let mask = (1 << self.rule.width()) - 1;
+            let cell = |position: i64| self.cells[position.rem_euclid(num_cells) as usize] as usize;
+
+            // Prime the window with everything but the rightmost cell of cell 0's neighborhood.
+            let mut neighborhood = (-radius..radius).fold(0, |acc, position| (acc << 1) | cell(position));
+
+            (0..num_cells)
+                .map(|position| {
+                    neighborhood = ((neighborhood << 1) | cell(position + radius)) & mask;
+                    self.rule.table[neighborhood]
+                })
+                .collect()
+        }
+
+        fn next_infinite(&mut self) -> Vec<bool> {
+            // A cell more than `radius` cells past the outermost live cells has an all-dead
+            // neighborhood, so it stays dead; the next generation spans `radius` more cells
+            // on each side than this one does.
+            let radius = self.rule.radius;
+            let mask = (1 << self.rule.width()) - 1;
+            let mut neighborhood = 0;
+
+            let next = (0..self.cells.len() + 2 * radius)
+                .map(|i| {
+                    // Slide the window to cover cells[i - 2 * radius..=i], whose center cell
+                    // is the one that ends up at next[i].
+                    let incoming = self.cells.get(i).cloned().unwrap_or(false);
+                    neighborhood = ((neighborhood << 1) | incoming as usize) & mask;
+                    self.rule.table[neighborhood]
+                })
+                .collect();
+
+            self.origin -= radius as i64;
+
+            next
+        }
+
+        /// Drops the dead cells from both ends of self.cells.
+        fn trim(&mut self) {
+            match self.cells.iter().position(|&cell| cell) {
+                Some(first_live_index) => {
+                    let last_live_index = self.cells.iter().rposition(|&cell| cell).unwrap();
+                    self.cells.truncate(last_live_index + 1);
+                    self.cells.drain(..first_live_index);
+                    self.origin += first_live_index as i64;
+                }
+                None => {
+                    self.cells.clear();
+                    self.origin = 0;
+                }
+            }
+        }
+
+        /// Returns the automaton's cells, the first of which lives at position `self.origin()`.
+        pub fn cells(&self) -> &[bool] {
+            &self.cells
+        }
+
+        pub fn origin(&self) -> i64 {
+            self.origin
+        }
+
+        pub fn generation(&self) -> u64 {
+            self.generation
+        }
+
+        /// Returns the positions of every live cell, from left to right.
+        pub fn live_cells<'a>(&'a self) -> impl Iterator<Item = i64> + 'a {
+            let origin = self.origin;
+
+            self.cells
+                .iter()
+                .enumerate()
+                .filter(|(_, &cell)| cell)
+                .map(move |(i, _)| origin + i as i64)
+        }
+    }
+
+    /// A record of the generations an automaton has been through, for rendering as a space-time diagram.
+    #[derive(Default)]
+    pub struct History {
+        /// Each recorded generation's number, origin and cells.
+        rows: Vec<(u64, i64, Vec<bool>)>,
+    }
+
+    impl History {
+        pub fn new() -> Self {
+            History { rows: vec![] }
+        }
+
+        /// Records `automaton`'s current generation.
+        pub fn record(&mut self, automaton: &Automaton) {
+            self.rows
+                .push((automaton.generation(), automaton.origin(), automaton.cells().to_vec()));
+        }
+
+        /// Returns the positions of the leftmost and rightmost cells in any recorded generation.
+        pub fn bounds(&self) -> Option<(i64, i64)> {
+            let min = self
+                .rows
+                .iter()
+                .filter(|(_, _, cells)| !cells.is_empty())
+                .map(|(_, origin, _)| *origin)
+                .min()?;
+            let max = self
+                .rows
+                .iter()
+                .filter(|(_, _, cells)| !cells.is_empty())
+                .map(|(_, origin, cells)| origin + cells.len() as i64 - 1)
+                .max()?;
+
+            Some((min, max))
+        }
+
+        /// Returns a grid, indexed as grid[x][y], whose y'th row is the y'th recorded generation's
+        /// cells from position `min` to position `max`.
+        pub fn to_vec(&self, min: i64, max: i64) -> Vec<Vec<bool>> {
+            (min..=max)
+                .map(|position| {
+                    self.rows
+                        .iter()
+                        .map(|(_, origin, cells)| {
+                            let index = position - origin;
+                            index >= 0 && cells.get(index as usize).cloned().unwrap_or(false)
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+
+        /// Renders every recorded generation as a row of `#`s and `.`s from position `min` to
+        /// position `max`, laid out like the table in day 12's writeup: each row is labeled with
+        /// its generation number, and the positions that are multiples of 10 are numbered
+        /// vertically across the top.
+        pub fn render(&self, min: i64, max: i64) -> String {
+            let label_width = self
+                .rows
+                .iter()
+                .map(|(generation, _, _)| generation.to_string().len())
+                .max()
+                .unwrap_or(0)
+                .max(2);
+            let grid = self.to_vec(min, max);
+
+            let column_labels = (min..=max)
+                .enumerate()
+                .filter(|(_, position)| position % 10 == 0)
+                .map(|(column, position)| (column, position.to_string().into_bytes()))
+                .collect::<Vec<_>>();
+            let header_height = column_labels.iter().map(|(_, label)| label.len()).max().unwrap_or(0);
+
+            let mut lines = vec![];
+
+            for line in 0..header_height {
+                let mut header = vec![b' '; grid.len()];
+
+                for (column, label) in &column_labels {
+                    // Labels are bottom-aligned, so that their last digits all share the last header line.
+                    if let Some(i) = (line + label.len()).checked_sub(header_height) {
+                        header[*column] = label[i];
+                    }
+                }
+
+                let header = String::from_utf8(header).unwrap();
+                lines.push(format!("{:w$}  {}", "", header, w = label_width).trim_end().to_string());
+            }
+
+            for (y, (generation, _, _)) in self.rows.iter().enumerate() {
+                let row = grid
+                    .iter()
+                    .map(|column| if column[y] { '#' } else { '.' })
+                    .collect::<String>();
+                lines.push(format!("{:>w$}: {}", generation, row, w = label_width));
+            }
+
+            lines.join("\n")
+        }
+
+        /// Writes the space-time diagram from position `min` to position `max` to `path` as a PPM image,
+        /// with one black pixel per live cell and one row of pixels per recorded generation.
+        pub fn write_ppm(&self, path: &str, min: i64, max: i64) -> io::Result<()> {
+            let pixels = self
+                .to_vec(min, max)
+                .iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .map(|&cell| if cell { [0, 0, 0] } else { [255, 255, 255] })
+                        .collect()
+                })
+                .collect::<Vec<Vec<Rgb>>>();
+
+            util::write_ppm(path, &pixels)
+        }
+    }
+}
+
+use automaton::{Automaton, Boundary, History, Rule};
+
 fn parse_input() -> cave::Cave {
-    let contents = fs::read_to_string("src/inputs/12.txt").unwrap();
+    parse_cave(&fs::read_to_string("src/inputs/12.txt").unwrap())
+}
+
+fn parse_cave(contents: &str) -> cave::Cave {
     let lines = contents.lines().collect::<Vec<&str>>();
+
     cave::Cave::new(
         lines[0]
             .replace("initial state: ", "")
             .chars()
             .map(|c| c == '#')
             .collect(),
-        lines.iter().skip(2).map(|line| GenerationRule::new(line)).collect(),
+        parse_rule(&lines[2..]),
     )
 }
 
+/// Parses either a list of LLCRR => N notes or a single line like "rule 30" into a Rule.
+fn parse_rule(lines: &[&str]) -> Rule {
+    lazy_static! {
+        static ref WOLFRAM_RE: Regex = Regex::new(r"^rule (?P<number>[0-9]+)$").unwrap();
+    }
+
+    if let Some(caps) = lines.first().and_then(|line| WOLFRAM_RE.captures(line.trim())) {
+        return Rule::elementary(caps.name("number").unwrap().as_str().parse().unwrap());
+    }
+
+    let generation_rules = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| GenerationRule::new(line))
+        .collect::<Vec<GenerationRule>>();
+    assert!(!generation_rules.is_empty(), "no rules to parse");
+
+    let mut rule = Rule::new(generation_rules[0].pattern.len() / 2);
+
+    for generation_rule in &generation_rules {
+        rule.set(&generation_rule.pattern, generation_rule.result);
+    }
+
+    rule
+}
+
 #[derive(PartialEq, Debug)]
 pub struct GenerationRule {
     pattern: Vec<bool>,
@@ -29,21 +405,19 @@ impl GenerationRule {
     /// in the last generation. These are written as LLCRR => N, where L are pots to the left,
     /// C is the current pot being considered, R are the pots to the right, and N is whether
     /// the current pot will have a plant in the next generation."
+    ///
+    /// Patterns of any odd length are accepted, for automata that look further than two pots away.
     fn new(input: &str) -> Self {
         lazy_static! {
-            static ref RE: Regex = Regex::new(r"(?P<pattern>[\.#]{5}) => (?P<result>[.#])").unwrap();
+            static ref RE: Regex = Regex::new(r"(?P<pattern>[\.#]+) => (?P<result>[.#])").unwrap();
         }
 
         let caps = RE.captures(input).unwrap();
+        let pattern = caps.name("pattern").unwrap().as_str();
+        assert!(pattern.len() % 2 == 1, "pattern {} has no center pot", pattern);
 
         GenerationRule {
-            pattern: caps
-                .name("pattern")
-                .unwrap()
-                .as_str()
-                .chars()
-                .map(|c| c == '#')
-                .collect(),
+            pattern: pattern.chars().map(|c| c == '#').collect(),
             result: caps.name("result").unwrap().as_str() == "#",
         }
     }
@@ -52,83 +426,46 @@ impl GenerationRule {
 mod cave {
     use super::*;
 
-    const PLANT_BUFFER_LENGTH: usize = 1000;
-
     /// After exploring a little, you discover a long tunnel that contains a row of small pots
     /// as far as you can see to your left and right. A few of them contain plants - someone
     /// is trying to grow things in these geothermally-heated caves.
     pub struct Cave {
-        plants: Vec<bool>,
-        rules: Vec<GenerationRule>,
-        first_plant_index: usize,
-        last_plant_index: usize,
+        plants: Automaton,
     }
 
     impl Cave {
-        pub fn new(initial_state: Vec<bool>, rules: Vec<GenerationRule>) -> Self {
-            let mut buf = vec![false; PLANT_BUFFER_LENGTH];
-
-            for i in 0..initial_state.len() {
-                buf[PLANT_BUFFER_LENGTH / 2 + i] = initial_state[i];
-            }
-
+        pub fn new(initial_state: Vec<bool>, rule: Rule) -> Self {
             Cave {
-                plants: buf,
-                rules,
-                first_plant_index: PLANT_BUFFER_LENGTH / 2,
-                last_plant_index: PLANT_BUFFER_LENGTH / 2 + initial_state.len(),
+                plants: Automaton::new(initial_state, rule, Boundary::Infinite),
             }
         }
 
-        /// Applies self.rules to self.plants.
+        pub fn plants(&self) -> &Automaton {
+            &self.plants
+        }
+
+        /// Advances the plants by one generation.
         /// Returns the sum of the indexes of the pots which contain a plant in the new generation.
         pub fn tick_generation(&mut self) -> i32 {
-            let mut new_generation = vec![];
-
-            let pots = self
-                .plants
-                .iter()
-                .skip(self.first_plant_index - 3)
-                .take((self.last_plant_index - self.first_plant_index) + 7)
-                .cloned()
-                .collect::<Vec<bool>>();
-
-            for window in pots.windows(5) {
-                for rule in &self.rules {
-                    if rule.pattern == window {
-                        new_generation.push(rule.result);
-                        break;
-                    }
-                }
-            }
-
-            let original_first_plant_index = self.first_plant_index;
-
-            for (i, &pot_has_plant) in new_generation.iter().enumerate() {
-                // - 3 because of the offset in the `.skip()` call earlier,
-                // + 2 because the actual plant whose fate is being determined
-                // is the one in the middle of this window of 5 pots.
-                let translated_index = i + original_first_plant_index - 3 + 2;
-
-                if pot_has_plant {
-                    if translated_index < self.first_plant_index {
-                        self.first_plant_index = translated_index;
-                    } else if translated_index > self.last_plant_index {
-                        self.last_plant_index = translated_index;
-                    }
-                }
+            self.plants.step();
+            self.plants.live_cells().sum::<i64>() as i32
+        }
+    }
+}
 
-                self.plants[translated_index] = pot_has_plant;
-            }
+/// Runs the cave described in the file at `path` for `num_generations` generations,
+/// recording every generation (including the initial state) along the way.
+pub fn twelve_history(path: &str, num_generations: usize) -> History {
+    let mut plant_cave = parse_cave(&fs::read_to_string(path).unwrap());
+    let mut history = History::new();
+    history.record(plant_cave.plants());
 
-            self.plants
-                .iter()
-                .enumerate()
-                .filter(|(_, &value)| value)
-                .map(|(index, _)| index as i32 - (PLANT_BUFFER_LENGTH / 2) as i32)
-                .sum()
-        }
+    for _ in 0..num_generations {
+        plant_cave.tick_generation();
+        history.record(plant_cave.plants());
     }
+
+    history
 }
 
 const FIFTY_BILLION: u64 = 50000000000;
@@ -202,4 +539,173 @@ mod test {
             }
         );
     }
+
+    fn render(automaton: &Automaton) -> String {
+        automaton
+            .cells()
+            .iter()
+            .map(|&cell| if cell { '#' } else { '.' })
+            .collect()
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut plant_cave = parse_cave(&fs::read_to_string("src/inputs/12_sample.txt").unwrap());
+
+        for _ in 0..19 {
+            plant_cave.tick_generation();
+        }
+
+        assert_eq!(plant_cave.tick_generation(), 325);
+    }
+
+    #[test]
+    fn test_rule_30() {
+        let mut automaton = Automaton::new(vec![true], Rule::elementary(30), Boundary::Infinite);
+        let mut rows = vec![];
+
+        for _ in 0..5 {
+            rows.push((automaton.origin(), render(&automaton)));
+            automaton.step();
+        }
+
+        assert_eq!(
+            rows,
+            vec![
+                (0, "#".to_string()),
+                (-1, "###".to_string()),
+                (-2, "##..#".to_string()),
+                (-3, "##.####".to_string()),
+                (-4, "##..#...#".to_string()),
+            ]
+        );
+
+        // Rule 30's population from a single live cell is OEIS A070952.
+        let mut automaton = Automaton::new(vec![true], Rule::elementary(30), Boundary::Infinite);
+        let mut populations = vec![];
+
+        for _ in 0..12 {
+            populations.push(automaton.live_cells().count());
+            automaton.step();
+        }
+
+        assert_eq!(populations, vec![1, 3, 3, 6, 4, 9, 5, 12, 7, 12, 11, 14]);
+    }
+
+    #[test]
+    fn test_rule_110() {
+        let mut automaton = Automaton::new(vec![true], Rule::elementary(110), Boundary::Infinite);
+        let mut rows = vec![];
+
+        for _ in 0..8 {
+            rows.push(render(&automaton));
+            automaton.step();
+        }
+
+        assert_eq!(
+            rows,
+            vec!["#", "##", "###", "##.#", "#####", "##...#", "###..##", "##.#.###"]
+        );
+        assert_eq!(automaton.generation(), 8);
+        assert_eq!(automaton.origin(), -8);
+    }
+
+    #[test]
+    fn test_wrap() {
+        // Rule 170 copies each cell's right-hand neighbor, so the pattern scrolls to the left.
+        let mut automaton = Automaton::new(
+            vec![true, true, false, false, false],
+            Rule::elementary(170),
+            Boundary::Wrap,
+        );
+        automaton.step();
+        assert_eq!(render(&automaton), "#...#");
+        automaton.step();
+        assert_eq!(render(&automaton), "...##");
+
+        // Rule 90 on a ring of 4 cells dies out after two generations.
+        let mut automaton = Automaton::new(vec![true, false, false, false], Rule::elementary(90), Boundary::Wrap);
+        automaton.step();
+        assert_eq!(render(&automaton), ".#.#");
+        automaton.step();
+        assert_eq!(render(&automaton), "....");
+    }
+
+    #[test]
+    fn test_rule_constructors() {
+        // Rule 150 is the totalistic rule "alive if an odd number of cells in the neighborhood are alive".
+        assert_eq!(Rule::totalistic(1, 0b1010), Rule::elementary(150));
+
+        let mut rule = Rule::new(1);
+        for pattern in &[
+            [true, false, false],
+            [false, true, true],
+            [false, true, false],
+            [false, false, true],
+        ] {
+            rule.set(pattern, true);
+        }
+        assert_eq!(rule, Rule::elementary(30));
+
+        assert_eq!(parse_rule(&["rule 110"]), Rule::elementary(110));
+        assert_eq!(
+            parse_rule(&["#.. => #", "..# => #", ".## => #", ".#. => #"]),
+            Rule::elementary(30)
+        );
+        assert_eq!(Rule::wolfram(2, 0).radius(), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_infinite_boundary_rejects_rules_that_fill_empty_space() {
+        Automaton::new(vec![true], Rule::elementary(1), Boundary::Infinite);
+    }
+
+    #[test]
+    #[should_panic(expected = "a row that wraps around needs at least one cell")]
+    fn test_wrap_boundary_rejects_empty_rows() {
+        Automaton::new(vec![], Rule::elementary(90), Boundary::Wrap);
+    }
+
+    #[test]
+    #[should_panic(expected = "no rules to parse")]
+    fn test_parse_rule_rejects_empty_input() {
+        parse_rule(&["", "  "]);
+    }
+
+    #[test]
+    fn test_history() {
+        let history = twelve_history("src/inputs/12_sample.txt", 20);
+
+        assert_eq!(history.bounds(), Some((-2, 34)));
+        assert_eq!(
+            history.render(-3, 35),
+            [
+                "                 1         2         3",
+                "       0         0         0         0",
+                " 0: ...#..#.#..##......###...###...........",
+                " 1: ...#...#....#.....#..#..#..#...........",
+                " 2: ...##..##...##....#..#..#..##..........",
+                " 3: ..#.#...#..#.#....#..#..#...#..........",
+                " 4: ...#.#..#...#.#...#..#..##..##.........",
+                " 5: ....#...##...#.#..#..#...#...#.........",
+                " 6: ....##.#.#....#...#..##..##..##........",
+                " 7: ...#..###.#...##..#...#...#...#........",
+                " 8: ...#....##.#.#.#..##..##..##..##.......",
+                " 9: ...##..#..#####....#...#...#...#.......",
+                "10: ..#.#..#...#.##....##..##..##..##......",
+                "11: ...#...##...#.#...#.#...#...#...#......",
+                "12: ...##.#.#....#.#...#.#..##..##..##.....",
+                "13: ..#..###.#....#.#...#....#...#...#.....",
+                "14: ..#....##.#....#.#..##...##..##..##....",
+                "15: ..##..#..#.#....#....#..#.#...#...#....",
+                "16: .#.#..#...#.#...##...#...#.#..##..##...",
+                "17: ..#...##...#.#.#.#...##...#....#...#...",
+                "18: ..##.#.#....#####.#.#.#...##...##..##..",
+                "19: .#..###.#..#.#.#######.#.#.#..#.#...#..",
+                "20: .#....##....#####...#######....#.#..##.",
+            ]
+            .join("\n")
+        );
+    }
 }
@@ -1,3 +1,9 @@
+pub mod ring;
+
+use std::fs;
+use std::io;
+use std::ops::{Add, Sub};
+
 use hashbrown::HashMap;
 
 pub fn frequencies<I, T>(x: I) -> HashMap<T, u32>
@@ -47,6 +53,71 @@ pub fn print_grid<T: std::fmt::Display>(grid: &[Vec<T>]) {
     print_grid_with_bounds(grid, 0, grid.len() - 1, 0, grid[0].len() - 1);
 }
 
+/// A table that can total up the values in any rectangle of a 2D grid in O(1) time.
+/// See https://en.wikipedia.org/wiki/Summed-area_table
+pub struct SummedAreaTable<T> {
+    /// sums[x][y] is the total of every value in the grid that's above and to the left of (x, y),
+    /// so sums has an extra leading row and column of zeroes, and we never have to special-case the grid's edges.
+    sums: Vec<Vec<T>>,
+}
+
+impl<T> SummedAreaTable<T>
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T>,
+{
+    /// Builds a table over `grid`, which is indexed as grid[x][y]. `T::default()` is used as zero.
+    pub fn new(grid: &[Vec<T>]) -> Self {
+        let width = grid.len();
+        let height = grid.first().map_or(0, |column| column.len());
+        let mut sums = vec![vec![T::default(); height + 1]; width + 1];
+
+        for x in 0..width {
+            for y in 0..height {
+                sums[x + 1][y + 1] = grid[x][y] + sums[x][y + 1] + sums[x + 1][y] - sums[x][y];
+            }
+        }
+
+        SummedAreaTable { sums }
+    }
+
+    pub fn width(&self) -> usize {
+        self.sums.len() - 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.sums[0].len() - 1
+    }
+
+    /// Returns the total of the values in the `width`x`height` rectangle whose top-left cell is (x, y).
+    pub fn rectangle_sum(&self, x: usize, y: usize, width: usize, height: usize) -> T {
+        let (right, bottom) = (x + width, y + height);
+
+        // Add before subtracting, so that unsigned sums never dip below zero along the way.
+        self.sums[right][bottom] + self.sums[x][y] - self.sums[x][bottom] - self.sums[right][y]
+    }
+}
+
+/// An (r, g, b) color.
+pub type Rgb = [u8; 3];
+
+/// Writes `grid` to `path` as a binary PPM image, one pixel per cell.
+/// Like `print_grid`, `grid` is indexed as grid[x][y].
+/// See http://netpbm.sourceforge.net/doc/ppm.html
+pub fn write_ppm(path: &str, grid: &[Vec<Rgb>]) -> io::Result<()> {
+    let width = grid.len();
+    let height = grid.first().map_or(0, |column| column.len());
+
+    let mut buf = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+    for y in 0..height {
+        for column in grid {
+            buf.extend_from_slice(&column[y]);
+        }
+    }
+
+    fs::write(path, buf)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -75,4 +146,33 @@ mod test {
 
         assert_eq!(frequencies("".chars()), HashMap::new());
     }
+
+    #[test]
+    fn test_write_ppm() {
+        let path = std::env::temp_dir().join("advent_2018_test_write_ppm.ppm");
+        let path = path.to_str().unwrap();
+
+        write_ppm(path, &[vec![[1, 2, 3], [4, 5, 6]], vec![[7, 8, 9], [10, 11, 12]]]).unwrap();
+
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[1, 2, 3, 7, 8, 9, 4, 5, 6, 10, 11, 12]);
+        assert_eq!(fs::read(path).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_summed_area_table() {
+        // Indexed as grid[x][y], so this is the transpose of how it looks.
+        let grid: Vec<Vec<u32>> = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9], vec![10, 11, 12]];
+        let table = SummedAreaTable::new(&grid);
+
+        assert_eq!((table.width(), table.height()), (4, 3));
+        assert_eq!(table.rectangle_sum(0, 0, 4, 3), 78);
+        assert_eq!(table.rectangle_sum(1, 1, 1, 1), 5);
+        assert_eq!(table.rectangle_sum(1, 0, 2, 3), 4 + 5 + 6 + 7 + 8 + 9);
+        assert_eq!(table.rectangle_sum(3, 1, 1, 2), 11 + 12);
+        assert_eq!(table.rectangle_sum(2, 2, 0, 1), 0);
+
+        let negative_grid = vec![vec![-1, -2], vec![-3, -4]];
+        assert_eq!(SummedAreaTable::new(&negative_grid).rectangle_sum(0, 1, 2, 1), -6);
+    }
 }
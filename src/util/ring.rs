@@ -0,0 +1,330 @@
+/// A circular doubly linked list whose nodes all live in one Vec.
+///
+/// Nodes point at each other by index rather than by pointer, so there's no unsafe code and no
+/// Rc<RefCell<>> juggling, and inserting or removing a node anywhere in the ring is O(1).
+/// Removed nodes' slots are recycled by later insertions, and the list of free slots has room for
+/// every node, so a ring created with enough capacity never allocates again.
+pub struct Ring<T> {
+    nodes: Vec<Node<T>>,
+    /// Indexes of the slots in `nodes` whose values have been removed.
+    free: Vec<usize>,
+    len: usize,
+}
+
+struct Node<T> {
+    /// None if this slot's value has been removed.
+    value: Option<T>,
+    prev: usize,
+    next: usize,
+}
+
+/// A handle to a node in a Ring. Handles to removed nodes must not be used again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl<T> Ring<T> {
+    pub fn new() -> Self {
+        Ring::with_capacity(0)
+    }
+
+    /// Returns an empty ring with room for `capacity` nodes, and for all of them to be removed.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Ring {
+            nodes: Vec::with_capacity(capacity),
+            free: Vec::with_capacity(capacity),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Puts `value` in a slot whose prev and next are yet to be filled in.
+    fn allocate(&mut self, value: T) -> usize {
+        self.len += 1;
+
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index].value = Some(value);
+                index
+            }
+            None => {
+                self.nodes.push(Node {
+                    value: Some(value),
+                    prev: 0,
+                    next: 0,
+                });
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Adds the first node to an empty ring; the node is its own neighbor on both sides.
+    /// Panics if the ring isn't empty.
+    pub fn insert_first(&mut self, value: T) -> NodeId {
+        assert!(self.is_empty(), "ring already has a first node");
+
+        let index = self.allocate(value);
+        self.nodes[index].prev = index;
+        self.nodes[index].next = index;
+
+        NodeId(index)
+    }
+
+    /// Inserts `value` immediately after `node`.
+    pub fn insert_after(&mut self, node: NodeId, value: T) -> NodeId {
+        self.assert_live(node);
+
+        let prev = node.0;
+        let next = self.nodes[prev].next;
+        let index = self.allocate(value);
+
+        self.nodes[index].prev = prev;
+        self.nodes[index].next = next;
+        self.nodes[prev].next = index;
+        self.nodes[next].prev = index;
+
+        NodeId(index)
+    }
+
+    /// Removes `node` from the ring and returns its value.
+    pub fn remove(&mut self, node: NodeId) -> T {
+        self.assert_live(node);
+
+        let Node { prev, next, .. } = self.nodes[node.0];
+        self.nodes[prev].next = next;
+        self.nodes[next].prev = prev;
+
+        self.free.push(node.0);
+        self.len -= 1;
+
+        self.nodes[node.0].value.take().unwrap()
+    }
+
+    pub fn next(&self, node: NodeId) -> NodeId {
+        self.assert_live(node);
+        NodeId(self.nodes[node.0].next)
+    }
+
+    pub fn prev(&self, node: NodeId) -> NodeId {
+        self.assert_live(node);
+        NodeId(self.nodes[node.0].prev)
+    }
+
+    /// Returns the node `n` steps after `node`, or `-n` steps before it if `n` is negative.
+    /// Walks whichever way around the ring is shorter.
+    pub fn rotate(&self, node: NodeId, n: isize) -> NodeId {
+        self.assert_live(node);
+
+        let steps_forward = n.rem_euclid(self.len as isize) as usize;
+        let mut index = node.0;
+
+        if steps_forward <= self.len / 2 {
+            for _ in 0..steps_forward {
+                index = self.nodes[index].next;
+            }
+        } else {
+            for _ in 0..self.len - steps_forward {
+                index = self.nodes[index].prev;
+            }
+        }
+
+        NodeId(index)
+    }
+
+    pub fn get(&self, node: NodeId) -> &T {
+        self.nodes[node.0].value.as_ref().expect("node has been removed")
+    }
+
+    pub fn get_mut(&mut self, node: NodeId) -> &mut T {
+        self.nodes[node.0].value.as_mut().expect("node has been removed")
+    }
+
+    /// Returns an iterator over every value in the ring, starting at `node` and going forward.
+    pub fn iter_from(&self, node: NodeId) -> Iter<'_, T> {
+        self.assert_live(node);
+
+        Iter {
+            ring: self,
+            index: node.0,
+            remaining: self.len,
+        }
+    }
+
+    /// Returns a cursor that starts out pointing at `node`.
+    pub fn cursor_mut(&mut self, node: NodeId) -> CursorMut<'_, T> {
+        self.assert_live(node);
+
+        CursorMut { ring: self, node }
+    }
+
+    fn assert_live(&self, node: NodeId) {
+        assert!(self.nodes[node.0].value.is_some(), "node has been removed");
+    }
+}
+
+impl<T> Default for Ring<T> {
+    fn default() -> Self {
+        Ring::new()
+    }
+}
+
+pub struct Iter<'a, T> {
+    ring: &'a Ring<T>,
+    index: usize,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = &self.ring.nodes[self.index];
+        self.index = node.next;
+        self.remaining -= 1;
+
+        node.value.as_ref()
+    }
+}
+
+/// A position in a Ring that can move around it and edit it as it goes.
+pub struct CursorMut<'a, T> {
+    ring: &'a mut Ring<T>,
+    node: NodeId,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// Returns the node the cursor is pointing at.
+    pub fn position(&self) -> NodeId {
+        self.node
+    }
+
+    pub fn current(&self) -> &T {
+        self.ring.get(self.node)
+    }
+
+    pub fn move_next(&mut self) {
+        self.node = self.ring.next(self.node);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.node = self.ring.prev(self.node);
+    }
+
+    /// Moves the cursor `n` steps forward, or `-n` steps back if `n` is negative.
+    pub fn rotate(&mut self, n: isize) {
+        self.node = self.ring.rotate(self.node, n);
+    }
+
+    /// Inserts `value` immediately after the cursor, which stays where it is.
+    pub fn insert_after(&mut self, value: T) -> NodeId {
+        self.ring.insert_after(self.node, value)
+    }
+
+    /// Removes the node the cursor is pointing at and moves the cursor to the next one.
+    /// If that was the ring's last node, the cursor mustn't be used again.
+    pub fn remove_current(&mut self) -> T {
+        let next = self.ring.next(self.node);
+        let value = self.ring.remove(self.node);
+        self.node = next;
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ring_of(values: &[i32]) -> (Ring<i32>, Vec<NodeId>) {
+        let mut ring = Ring::with_capacity(values.len());
+        let mut nodes = vec![ring.insert_first(values[0])];
+
+        for &value in &values[1..] {
+            let last = *nodes.last().unwrap();
+            nodes.push(ring.insert_after(last, value));
+        }
+
+        (ring, nodes)
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let (mut ring, nodes) = ring_of(&[1, 2, 3, 4]);
+        assert_eq!(ring.len(), 4);
+        assert_eq!(
+            ring.iter_from(nodes[2]).cloned().collect::<Vec<i32>>(),
+            vec![3, 4, 1, 2]
+        );
+
+        assert_eq!(ring.remove(nodes[1]), 2);
+        assert_eq!(ring.iter_from(nodes[0]).cloned().collect::<Vec<i32>>(), vec![1, 3, 4]);
+        assert_eq!(ring.prev(nodes[2]), nodes[0]);
+
+        // The removed node's slot gets reused.
+        let five = ring.insert_after(nodes[3], 5);
+        assert_eq!(five, nodes[1]);
+        assert_eq!(
+            ring.iter_from(nodes[0]).cloned().collect::<Vec<i32>>(),
+            vec![1, 3, 4, 5]
+        );
+
+        for &node in &[nodes[0], nodes[2], nodes[3], five] {
+            ring.remove(node);
+        }
+        assert!(ring.is_empty());
+
+        let six = ring.insert_first(6);
+        assert_eq!(ring.next(six), six);
+        *ring.get_mut(six) += 1;
+        assert_eq!(ring.get(six), &7);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let (ring, nodes) = ring_of(&[0, 1, 2, 3, 4]);
+
+        assert_eq!(ring.rotate(nodes[0], 0), nodes[0]);
+        assert_eq!(ring.rotate(nodes[0], 2), nodes[2]);
+        assert_eq!(ring.rotate(nodes[0], 4), nodes[4]);
+        assert_eq!(ring.rotate(nodes[1], -2), nodes[4]);
+        assert_eq!(ring.rotate(nodes[1], 11), nodes[2]);
+        assert_eq!(ring.rotate(nodes[1], -11), nodes[0]);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let (mut ring, nodes) = ring_of(&[0, 1, 2]);
+        let mut cursor = ring.cursor_mut(nodes[0]);
+
+        cursor.move_prev();
+        assert_eq!(*cursor.current(), 2);
+        cursor.insert_after(3);
+        cursor.move_next();
+        assert_eq!(*cursor.current(), 3);
+
+        cursor.rotate(-2);
+        assert_eq!(cursor.remove_current(), 1);
+        assert_eq!(*cursor.current(), 2);
+        let position = cursor.position();
+
+        assert_eq!(ring.iter_from(position).cloned().collect::<Vec<i32>>(), vec![2, 3, 0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_removed_node() {
+        let (mut ring, nodes) = ring_of(&[0, 1, 2]);
+        ring.remove(nodes[1]);
+        ring.next(nodes[1]);
+    }
+}